@@ -0,0 +1,69 @@
+//! Ship classes, read from [`CLASSES_FILE`] in the assets folder when the game starts.
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::rules::Hull;
+
+pub const CLASSES_FILE: &str = "ships.ron";
+
+/// Sprite paths, relative to the assets folder, for each side's ships of a class.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ShipSprites {
+    pub player: String,
+    pub enemy: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ShipClass {
+    pub name: String,
+    pub hull: Hull,
+    pub sprites: ShipSprites,
+    /// Sprite scale.
+    pub scale: f32,
+}
+
+/// Every class, in the order the options screen cycles through them. The file lists them
+/// from weakest to strongest, which is how the difficulties pick the enemy class.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShipClasses(Vec<ShipClass>);
+
+impl ShipClasses {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let classes: Vec<ShipClass> = ron::from_str(&fs::read_to_string(path)?)?;
+        if classes.is_empty() {
+            return Err("no ship classes defined".into());
+        }
+        Ok(ShipClasses(classes))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ShipClass> {
+        self.0.iter()
+    }
+
+    /// The class called `name`. Every class a match can use is checked for at startup, so
+    /// once it is under way this only fails on a bug.
+    pub fn get(&self, name: &str) -> Option<&ShipClass> {
+        self.0.iter().find(|class| class.name == name)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.0.iter().map(|class| class.name.as_str()).collect()
+    }
+
+    /// The class ranked `rank` of `ranks`, counting from the first class in the file to the
+    /// last, e.g. rank 1 of 3 is the middle one.
+    pub fn ranked(&self, rank: usize, ranks: usize) -> &ShipClass {
+        let last = self.0.len() - 1;
+        &self.0[rank * last / (ranks - 1).max(1)]
+    }
+
+    /// The class after `name`, wrapping around to the first.
+    pub fn next(&self, name: &str) -> &ShipClass {
+        let index = self.0.iter().position(|class| class.name == name);
+        &self.0[index.map_or(0, |i| (i + 1) % self.0.len())]
+    }
+}
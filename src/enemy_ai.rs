@@ -1,119 +1,146 @@
 use bevy::prelude::*;
-use heron::prelude::*;
+
+use crate::rules::{direction_rotation, heading, Action, Aim, GameState, ShipId, Side, Steer};
+use crate::{AppState, Orders};
+
+/// Decides what an AI-controlled ship does on its turn, from the state of the battle alone.
+pub trait EnemyBrain: Send + Sync {
+    /// The actions `me` takes this turn, in order.
+    fn plan(&self, state: &GameState, me: ShipId) -> Vec<Action>;
+}
+
+/// The brain playing an enemy ship, a [`ChaserBrain`] unless set otherwise.
+#[derive(Component)]
+pub struct Brain(pub Box<dyn EnemyBrain>);
+
+impl Default for Brain {
+    fn default() -> Self {
+        Brain(Box::new(ChaserBrain))
+    }
+}
 
 pub fn think(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut player_turn: ResMut<crate::PlayerTurn>,
-    player: Query<(With<crate::Player>, &Transform)>,
-    mut enemy: Query<(
-        With<crate::Enemy>,
-        &mut Transform,
-        &mut crate::Direction,
-        &mut crate::ActionPoints,
-        Without<crate::Player>,
-    )>,
+    mut orders: Orders,
+    app_state: Res<State<AppState>>,
+    brains: Query<(&ShipId, &Brain)>,
+) {
+    if app_state.current() != &AppState::InGame {
+        return;
+    }
+    play(Side::Enemy, &mut orders, |ship| {
+        brains
+            .iter()
+            .find(|(&id, _)| id == ship)
+            .map(|(_, brain)| brain.0.as_ref())
+    });
+}
+
+/// Plays every ship of `side` that can act with its brain, if it is that side's turn. Ships
+/// are played in `ShipId` order so a recording plays back the same.
+pub fn play<'a>(
+    side: Side,
+    orders: &mut Orders,
+    brain: impl Fn(ShipId) -> Option<&'a dyn EnemyBrain>,
 ) {
-    if player_turn.0 == crate::Turn::Enemy {
-        player_turn.0 = crate::Turn::Player;
-        // println!("{:?}", player_turn.0);
-        for (_, p) in player.iter() {
-            for (_, mut e, mut direction, mut ap, _) in enemy.iter_mut() {
-                let mut rotation_factor = 0.0;
-                let mut movement_factor = 0.0;
-
-                let player_q = get_player_direction(p, &e);
-
-                if player_q == direction.d {
-                    movement_factor += (crate::FORWARD_MOVE_DIST) * 0.50;
-                    if ap.value < 3 {
-                        ap.value += 1;
-                    }
-                } else if (player_q - direction.d).abs() > 5 {
-                    //TODO special case
-                    //turn left
-                    if direction.d == 0 {
-                        direction.d = 7;
-                    } else {
-                        direction.d -= 1;
-                    }
-                    movement_factor += (crate::FORWARD_MOVE_DIST) * 0.50;
-                    rotation_factor += 1.0;
-
-                    if ap.value < 3 {
-                        ap.value += 1;
-                    }
-                } else if direction.d > player_q {
-                    //turn left
-                    if direction.d == 0 {
-                        direction.d = 7;
-                    } else {
-                        direction.d -= 1;
-                    }
-                    movement_factor += (crate::FORWARD_MOVE_DIST) * 0.50;
-                    rotation_factor += 1.0;
-
-                    if ap.value < 3 {
-                        ap.value += 1;
-                    }
-                } else {
-                    //turn right
-                    if direction.d == 7 {
-                        direction.d = 0;
-                    } else {
-                        direction.d += 1;
-                    }
-                    movement_factor += (crate::FORWARD_MOVE_DIST) * 0.50;
-                    rotation_factor -= 1.0;
-
-                    if ap.value < 3 {
-                        ap.value += 1;
-                    }
-                }
-
-                for _ in 0..2 {
-                    let rotation_delta =
-                        Quat::from_rotation_z(rotation_factor * f32::to_radians(22.5));
-
-                    // move and rotate
-                    let movement_direction = e.rotation * Vec3::Y;
-                    let movement_distance = movement_factor * 1.0;
-                    let translation_delta = movement_direction * movement_distance;
-                    e.translation += translation_delta;
-                    e.rotation *= rotation_delta;
-                }
-
-                // map boundaries
-                let extents = Vec3::from((crate::BOUNDS / 2.0, 0.0));
-                e.translation = e.translation.min(extents).max(-extents);
-
-                //GUN
-                if ap.value == 3 {
-                    commands
-                        .spawn_bundle(SpriteBundle {
-                            texture: asset_server.load("textures/ship_parts/cannonBall.png"),
-                            transform: e.clone(),
-                            ..Default::default()
-                        })
-                        .insert(crate::CannonBall)
-                        .insert(RigidBody::Dynamic)
-                        .insert(CollisionShape::Sphere { radius: 10.0 })
-                        .insert(
-                            CollisionLayers::none()
-                                .with_group(crate::Layer::CannonBall)
-                                .with_masks(&[crate::Layer::Rock, crate::Layer::Player]),
-                        )
-                        .insert(Velocity::from_linear(crate::get_gun_arc(player_q) * 1000.0));
-
-                    ap.value -= 3;
-                }
-            }
+    if orders.state.phase.acting_side() != Some(side) {
+        return;
+    }
+
+    let ships: Vec<ShipId> = orders
+        .state
+        .fleet(side)
+        .map(|(id, _)| id)
+        .filter(|&id| orders.state.can_act(id))
+        .collect();
+    for ship in ships {
+        let actions = match brain(ship) {
+            Some(brain) => brain.plan(&orders.state, ship),
+            None => continue,
+        };
+        for action in actions {
+            orders.issue(action);
         }
     }
 }
 
+/// How far off the line of fire, in hull radii, a target may be for it to be fired on.
+const LINE_UP_SLACK: f32 = 2.0;
+
+/// Chases the opposing fleet. Once in gun range it turns to bring its target abeam, and
+/// fires the guns on that side whenever they are ready and bear on the target.
+pub struct ChaserBrain;
+
+impl EnemyBrain for ChaserBrain {
+    fn plan(&self, state: &GameState, me: ShipId) -> Vec<Action> {
+        chase(state, me)
+    }
+}
+
+fn chase(state: &GameState, me: ShipId) -> Vec<Action> {
+    let ship = state.ship(me);
+    let player = match state.fleet(ship.side.opponent()).next() {
+        Some((_, player)) => player,
+        None => return vec![],
+    };
+
+    let mut actions = vec![];
+    let player_q = get_player_direction(player.position, ship.position);
+    let direction = ship.direction;
+
+    //GUN
+    // Fire the side facing the player once they are near enough its line of fire to be hit.
+    let offset = player.position - ship.position;
+    let facing = if heading(ship.rotation).perp_dot(offset) > 0.0 {
+        direction - 2
+    } else {
+        direction + 2
+    };
+    let line = heading(direction_rotation(facing));
+    let in_range = line.dot(offset) <= ship.hull.guns.range;
+    let lined_up = line.perp_dot(offset).abs() <= player.hull.radius * LINE_UP_SLACK;
+    if in_range && lined_up && ship.action_points >= ship.fire_cost() {
+        actions.push(Action::Fire {
+            ship: me,
+            aim: Aim::Heading(facing.rem_euclid(8)),
+        });
+    }
+
+    // Head for the player until in range, then for either course with them abeam. Rather a
+    // point off course on a fast point of sail than straight into the wind.
+    let courses = if offset.length() > ship.hull.guns.range {
+        vec![player_q]
+    } else {
+        vec![player_q + 2, player_q - 2]
+    };
+    let wind = state.wind();
+    let score = |steer: &Steer| {
+        let delta = match steer {
+            Steer::Forward => 0,
+            Steer::Left => -1,
+            Steer::Right => 1,
+        };
+        let heading = (direction + delta).rem_euclid(8);
+        let off_course = courses
+            .iter()
+            .map(|course| {
+                let off_course = (heading - course).rem_euclid(8);
+                off_course.min(8 - off_course)
+            })
+            .min()
+            .unwrap_or(0);
+        off_course as f32 - wind.speed_factor(heading)
+    };
+    let steer = [Steer::Forward, Steer::Left, Steer::Right]
+        .into_iter()
+        .min_by(|a, b| score(a).partial_cmp(&score(b)).unwrap())
+        .unwrap();
+    actions.push(Action::Steer { ship: me, steer });
+
+    actions
+}
+
 //Try and figure out where the enemy is
-fn get_player_direction(p: &Transform, e: &Transform) -> i32 {
+fn get_player_direction(p: Vec2, e: Vec2) -> i32 {
     //Do we turn left or right?
     //Knowing our direction, which quadrant are they in
     //ex we are 7, they are 4
@@ -122,16 +149,16 @@ fn get_player_direction(p: &Transform, e: &Transform) -> i32 {
     //Do we shoot? Yes.
 
     let mut y = 0;
-    if p.translation.y > e.translation.y {
+    if p.y > e.y {
         y = 1
-    } else if p.translation.y < e.translation.y {
+    } else if p.y < e.y {
         y = -1
     }
 
     let mut x = 0;
-    if p.translation.x > e.translation.x {
+    if p.x > e.x {
         x = 1
-    } else if p.translation.x < e.translation.x {
+    } else if p.x < e.x {
         x = -1
     }
 
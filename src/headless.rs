@@ -0,0 +1,101 @@
+//! `ship_game --headless [--seed N] [--enemies N] [--friendly-fire] [--max-turns N] [--record FILE | --replay FILE]`
+//! plays a whole match without a window or GPU.
+//!
+//! The player's ship is steered by the same AI as the enemies. The outcome and the rounds
+//! and turns played are printed to stdout once the match ends.
+
+use std::time::Duration;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use heron::PhysicsSteps;
+
+use crate::enemy_ai::ChaserBrain;
+use crate::replay::Playback;
+use crate::rules::{GameState, Side};
+
+const DEFAULT_MAX_TURNS: u32 = 500;
+
+/// Simulated time per frame, so matches run as fast as the CPU allows.
+const FRAME_TIME: Duration = Duration::from_millis(16);
+
+/// Frames a turn may take before the match is considered stuck, e.g. waiting on a
+/// cannonball that never lands.
+const MAX_FRAMES_PER_TURN: u32 = 2000;
+
+pub struct MatchStats {
+    pub max_turns: u32,
+    /// Frames since `GameState::turn_number` last changed.
+    pub frames_this_turn: u32,
+    pub last_turn: u32,
+}
+
+pub fn build(app: &mut App, playing_back: bool) {
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(PhysicsSteps::every_frame(FRAME_TIME))
+        .insert_resource(MatchStats {
+            max_turns: crate::cli_value("--max-turns").unwrap_or(DEFAULT_MAX_TURNS),
+            frames_this_turn: 0,
+            last_turn: 0,
+        })
+        .init_resource::<crate::Textures>()
+        .add_system(check_turn_limit.after(crate::TurnLabel::Enemy))
+        .add_system_set(SystemSet::on_enter(crate::AppState::Win).with_system(report_outcome))
+        .add_system_set(SystemSet::on_enter(crate::AppState::Lose).with_system(report_outcome))
+        .add_system_set(SystemSet::on_enter(crate::AppState::Draw).with_system(report_outcome));
+
+    if playing_back {
+        app.add_system(crate::replay::play_back.label(crate::TurnLabel::Player));
+    } else {
+        app.add_system(autopilot.label(crate::TurnLabel::Player));
+    }
+}
+
+/// Plays the player's ships with the enemy AI.
+fn autopilot(mut orders: crate::Orders) {
+    crate::enemy_ai::play(Side::Player, &mut orders, |_| Some(&ChaserBrain));
+}
+
+fn check_turn_limit(
+    mut stats: ResMut<MatchStats>,
+    mut exit: EventWriter<AppExit>,
+    playback: Option<Res<Playback>>,
+    state: Res<GameState>,
+) {
+    if state.turn_number != stats.last_turn {
+        stats.last_turn = state.turn_number;
+        stats.frames_this_turn = 0;
+    }
+    stats.frames_this_turn += 1;
+
+    // A replay cut short before the match was decided never reaches Win, Lose or Draw.
+    let replay_ended = playback.is_some_and(|playback| playback.is_finished()) && !state.is_over();
+    let stuck = stats.frames_this_turn > MAX_FRAMES_PER_TURN && !state.is_over();
+    // The last turn's cannonballs land first, as they may still decide the match.
+    let out_of_turns = state.turn_number >= stats.max_turns
+        && !state.is_over()
+        && state.phase.acting_side().is_some();
+    if out_of_turns || replay_ended || stuck {
+        println!(
+            "outcome: Unfinished, rounds: {}, turns: {}",
+            state.round.current, state.turn_number
+        );
+        exit.send(AppExit);
+    }
+}
+
+fn report_outcome(
+    app_state: Res<State<crate::AppState>>,
+    report: Res<crate::GameOverEvent>,
+    state: Res<GameState>,
+    mut exit: EventWriter<AppExit>,
+) {
+    println!(
+        "outcome: {:?}, reason: {:?}, rounds: {}, turns: {}",
+        app_state.current(),
+        report.outcome.reason,
+        report.rounds,
+        state.turn_number
+    );
+    exit.send(AppExit);
+}
@@ -1,11 +1,32 @@
+use std::collections::HashMap;
+
+use bevy::asset::FileAssetIo;
 use bevy::core::FixedTimestep;
+use bevy::ecs::system::SystemParam;
 use bevy::math::const_vec2;
 use bevy::prelude::*;
+use classes::{ShipClass, ShipClasses};
 use heron::prelude::*;
+use heron::{PhysicsSteps, SensorShape};
+use menu::MatchSettings;
 use rand::Rng;
-use std::string::String;
+use replay::{Playback, Record, Recorder, Replay};
+use rng::GameRng;
+use rules::{
+    Action, Aim, Ammo, BattleStats, DamageKind, GameEvent, GameState, Outcome, Phase, Rock, Ship,
+    ShipId, Side, Steer, Wind, Wreck,
+};
+use save::{Autosave, SavedBattle};
+use serde::{Deserialize, Serialize};
 
+mod classes;
 mod enemy_ai;
+mod headless;
+mod menu;
+mod replay;
+mod rng;
+mod rules;
+mod save;
 mod ui;
 
 const TIME_STEP: f32 = 0.1;
@@ -14,66 +35,252 @@ const WINDOW_HEIGHT: f32 = 750.0;
 const WINDOW_WIDTH: f32 = 750.0;
 const BOUNDS: Vec2 = const_vec2!([WINDOW_HEIGHT, WINDOW_WIDTH]);
 
-const FORWARD_MOVE_DIST: f32 = 10.0;
+const CANNONBALL_RADIUS: f32 = 10.0;
+const CANNONBALL_SPEED: f32 = 1000.0;
+/// Flying time a cannonball gets on top of what its range takes at full speed.
+const RANGE_SLACK_SECONDS: f32 = 0.5;
+
+const MAX_ROUNDS: u32 = 10;
 
-const SHIP_SIZE: f32 = 0.15;
+/// How long a sunk ship takes to disappear beneath the waves.
+const SINKING_SECONDS: f32 = 2.0;
+const SPLASH_SECONDS: f32 = 1.0;
 
-const MAX_ROUNDS: i32 = 10;
-const TIMESTEP_1_PER_SECOND: f64 = 60.0 / 60.0;
+/// Spacing between neighbouring ships on each wing of the enemy echelon.
+const ECHELON_RIGHT: Vec2 = const_vec2!([110.0, 20.0]);
+const ECHELON_LEFT: Vec2 = const_vec2!([-20.0, -110.0]);
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum AppState {
     InGame,
     Win,
     Lose,
+    Draw,
+    MainMenu,
+    Options,
+    Paused,
 }
 
 fn main() {
-    App::new()
-        .add_state(AppState::InGame)
-        .add_system_set(SystemSet::on_enter(AppState::Lose).with_system(ui::end_message))
-        .add_system_set(SystemSet::on_enter(AppState::Win).with_system(ui::end_message))
-        .add_system_set(SystemSet::on_update(AppState::Win).with_system(ui::button_system))
-        .add_system_set(SystemSet::on_update(AppState::Lose).with_system(ui::button_system))
-        .insert_resource(WindowDescriptor {
+    let headless = std::env::args().any(|arg| arg == "--headless");
+    let playback = cli_value::<String>("--replay").map(|path| {
+        Replay::load(&path).unwrap_or_else(|err| panic!("could not load replay {}: {}", path, err))
+    });
+    let loaded = cli_value::<String>("--load").map(|path| {
+        SavedBattle::load(&path)
+            .unwrap_or_else(|err| panic!("could not load battle {}: {}", path, err))
+    });
+    // Read up front rather than through the `AssetServer`, as matches can't start without them.
+    let classes_path = FileAssetIo::get_root_path()
+        .join("assets")
+        .join(classes::CLASSES_FILE);
+    let classes = ShipClasses::load(&classes_path).unwrap_or_else(|err| {
+        panic!(
+            "could not load ship classes {}: {}",
+            classes_path.display(),
+            err
+        )
+    });
+    let ship_class =
+        cli_value::<String>("--ship").unwrap_or_else(|| classes.names()[0].to_string());
+    // Unknown classes are caught here rather than once the match has started.
+    let mut used_classes = vec![("--ship", ship_class.clone())];
+    if let Some(replay) = &playback {
+        used_classes.push(("the replay", replay.settings.ship_class.clone()));
+    }
+    if let Some(saved) = &loaded {
+        for ship in saved.state.ships.iter() {
+            used_classes.push(("the saved battle", ship.class.clone()));
+        }
+    }
+    for (source, name) in used_classes {
+        if classes.get(&name).is_none() {
+            panic!(
+                "unknown ship class {} in {}, expected one of {}",
+                name,
+                source,
+                classes.names().join(", ")
+            );
+        }
+    }
+    // The menu is skipped when there is already a battle to show.
+    let first_state = if headless || playback.is_some() || loaded.is_some() {
+        AppState::InGame
+    } else {
+        AppState::MainMenu
+    };
+
+    let mut app = App::new();
+    // The map seed and the recording are both reset when each match starts.
+    if let Some(path) = cli_value::<String>("--record") {
+        app.insert_resource(Recorder::new(path.into()))
+            .add_system(replay::save_recording);
+    }
+    app.insert_resource(GameRng::new(0))
+        .insert_resource(MatchSettings {
+            seed: cli_value("--seed"),
+            ship_class,
+            fleet_size: cli_value("--enemies")
+                .unwrap_or(1)
+                .clamp(1, menu::MAX_FLEET_SIZE),
+            friendly_fire: std::env::args().any(|arg| arg == "--friendly-fire"),
+            ..Default::default()
+        })
+        .insert_resource(classes);
+
+    if headless {
+        headless::build(&mut app, playback.is_some());
+    } else {
+        app.insert_resource(WindowDescriptor {
             title: "Rusty Pirates".to_string(),
             width: WINDOW_WIDTH,
             height: WINDOW_HEIGHT,
             ..Default::default()
         })
+        .insert_resource(ClearColor(Color::rgb(0.00, 0.50, 0.70)))
+        .add_plugins(DefaultPlugins)
+        .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(ui::setup))
+        .add_system_set(SystemSet::on_enter(AppState::Lose).with_system(ui::end_message))
+        .add_system_set(SystemSet::on_enter(AppState::Win).with_system(ui::end_message))
+        .add_system_set(SystemSet::on_enter(AppState::Draw).with_system(ui::end_message))
+        .add_system_set(SystemSet::on_exit(AppState::Lose).with_system(ui::clear_end_screen))
+        .add_system_set(SystemSet::on_exit(AppState::Win).with_system(ui::clear_end_screen))
+        .add_system_set(SystemSet::on_exit(AppState::Draw).with_system(ui::clear_end_screen))
+        .add_system_set(SystemSet::on_update(AppState::Win).with_system(ui::button_system))
+        .add_system_set(SystemSet::on_update(AppState::Lose).with_system(ui::button_system))
+        .add_system_set(SystemSet::on_update(AppState::Draw).with_system(ui::button_system))
         .add_system_set(
-            SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(TIMESTEP_1_PER_SECOND))
-                .with_system(enemy_ai::think),
+            SystemSet::on_enter(AppState::MainMenu)
+                .with_system(menu::main_menu)
+                .with_system(clear_match),
         )
-        .insert_resource(PlayerTurn(Turn::Player))
-        .insert_resource(ClearColor(Color::rgb(0.00, 0.50, 0.70)))
-        .insert_resource(Round { count: MAX_ROUNDS })
-        .add_plugin(PhysicsPlugin::default())
+        .add_system_set(SystemSet::on_exit(AppState::MainMenu).with_system(menu::clear_menu))
+        .add_system_set(SystemSet::on_update(AppState::MainMenu).with_system(menu::button_system))
+        .add_system_set(SystemSet::on_enter(AppState::Options).with_system(menu::options_menu))
+        .add_system_set(SystemSet::on_exit(AppState::Options).with_system(menu::clear_menu))
+        .add_system_set(
+            SystemSet::on_update(AppState::Options)
+                .with_system(menu::button_system)
+                .with_system(menu::edit_seed)
+                .with_system(menu::option_text_system),
+        )
+        .init_resource::<Autosave>()
+        .add_system_set(
+            SystemSet::on_update(AppState::InGame)
+                .with_system(save::save_battle)
+                .with_system(menu::toggle_pause),
+        )
+        .add_system_set(
+            SystemSet::on_enter(AppState::Paused)
+                .with_system(menu::pause_menu)
+                .with_system(menu::pause_physics),
+        )
+        .add_system_set(
+            SystemSet::on_update(AppState::Paused)
+                .with_system(menu::button_system)
+                .with_system(menu::toggle_pause),
+        )
+        .add_system_set(
+            SystemSet::on_exit(AppState::Paused)
+                .with_system(menu::clear_menu)
+                .with_system(menu::resume_physics),
+        )
+        // The pause menu makes way for the options screen while it is open.
+        .add_system_set(SystemSet::on_pause(AppState::Paused).with_system(menu::clear_menu))
+        .add_system_set(SystemSet::on_resume(AppState::Paused).with_system(menu::pause_menu))
+        .add_startup_system_to_stage(StartupStage::PreStartup, load_textures)
         .add_startup_system(setup_camera)
-        .add_startup_system(setup_rocks)
-        .add_startup_system(ui::setup)
         .add_system(ui::enemy_text_update_system)
-        .add_system(ui::game_over)
         .add_system(ui::player_text_update_system)
-        .add_startup_system(spawn_player_ship)
-        .add_system(
+        .add_system(ui::shot_text_update_system)
+        .add_system(ui::wind_text_update_system)
+        .add_system(ui::status_text_update_system)
+        .add_system(ui::blocked_text_update_system)
+        .add_system(ui::round_text_update_system);
+
+        let controls = SystemSet::new().with_run_criteria(FixedTimestep::step(TIME_STEP as f64));
+        if playback.is_some() {
+            app.add_system_set(controls.with_system(replay::play_back.label(TurnLabel::Player)));
+        } else {
+            app.init_resource::<KeyPresses>()
+                .add_system(latch_key_presses.before(TurnLabel::Player))
+                .add_system_set(controls.with_system(ship_movement.label(TurnLabel::Player)));
+        }
+    }
+
+    if let Some(replay) = playback {
+        app.insert_resource(Playback::new(replay));
+    } else {
+        app.add_system(
             enemy_ai::think
                 .label(TurnLabel::Enemy)
+                .after(TurnLabel::Player),
+        );
+    }
+
+    if let Some(saved) = loaded {
+        app.insert_resource(saved.state.clone())
+            .insert_resource(saved);
+    } else {
+        app.insert_resource(GameState::new(BOUNDS, MAX_ROUNDS));
+    }
+
+    app.add_state(first_state)
+        .add_system_set(
+            SystemSet::on_enter(AppState::InGame)
+                .with_system(clear_match.label(MatchLabel::Reset))
+                .with_system(reset_match.label(MatchLabel::Reset))
+                .with_system(
+                    start_match
+                        .after(MatchLabel::Reset)
+                        .before(TurnLabel::Player),
+                ),
+        )
+        .add_event::<GameEvent>()
+        .add_event::<GameOverEvent>()
+        .add_event::<DamageEvent>()
+        .add_event::<SplashEvent>()
+        .add_plugin(PhysicsPlugin::default())
+        .add_system(announce_game_over)
+        .init_resource::<ui::PendingGameOver>()
+        .add_system(ui::game_over)
+        .add_system(sync_ships.after(TurnLabel::Enemy))
+        .add_system(sink_ships.after(TurnLabel::Enemy))
+        .add_system(fade_out)
+        .add_system(spawn_cannonballs.after(TurnLabel::Enemy))
+        .add_system(
+            cannon_fodder
+                .label(CombatLabel::Collisions)
                 .before(TurnLabel::Player),
         )
-        .add_startup_system(spawn_enemy_ships)
-        .add_system_set(
-            SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(TIME_STEP as f64))
-                .with_system(ship_movement.label(TurnLabel::Player)),
+        .add_system(
+            apply_damage
+                .after(CombatLabel::Collisions)
+                .before(TurnLabel::Player),
         )
-        .add_system(ship_collision)
-        .add_system(cannon_fodder)
-        .add_plugins(DefaultPlugins)
+        .add_system(
+            lost_cannonballs
+                .label(CombatLabel::Collisions)
+                .before(TurnLabel::Player),
+        )
+        .add_system(
+            apply_misses
+                .after(CombatLabel::Collisions)
+                .before(TurnLabel::Player),
+        )
+        .add_system(log_game_events)
         .run();
 }
 
+/// Parses the value following `flag` on the command line, e.g. `--seed 42`.
+pub fn cli_value<T: std::str::FromStr>(flag: &str) -> Option<T> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .and_then(|value| value.parse().ok())
+}
+
 // players
 #[derive(Component, Debug, Clone, Copy, PartialEq)]
 pub struct Player;
@@ -84,438 +291,929 @@ pub struct Enemy;
 #[derive(Component, Debug, Clone, Copy, PartialEq)]
 pub struct CannonBall;
 
+/// The ship entity that fired a cannonball.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Owner(pub Entity);
+
+/// Marks what is left of a sunk ship.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Wreckage;
+
+/// Shrinks the sprite from `from` to `to` times its size while fading it out, then
+/// despawns the entity.
+#[derive(Component, Debug, Clone)]
+pub struct Fading {
+    pub timer: Timer,
+    pub from: f32,
+    pub to: f32,
+}
+
+/// How far a cannonball flies from where it left the muzzle before it drops into the sea.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Range {
+    pub origin: Vec2,
+    pub distance: f32,
+    /// Seconds of physics time left before the ball drops anyway, should anything slow it
+    /// down short of `distance`.
+    pub time_left: f32,
+}
+
 #[derive(Component)]
 pub struct Health {
     pub value: i32,
 }
 
+#[derive(Component)]
+pub struct Sails {
+    pub value: i32,
+}
+
+#[derive(Component)]
+pub struct Rudder {
+    pub value: i32,
+}
+
+#[derive(Component)]
+pub struct Crew {
+    pub value: i32,
+}
+
 #[derive(Component)]
 pub struct ActionPoints {
     pub value: i32,
 }
 
 ///0 => up
-///1 => up_left
-///2 => left
-///3 => down_left
+///1 => up_right
+///2 => right
+///3 => down_right
 ///4 => down
-///5 => down_right
-///6 => right
-///7 => up_right
+///5 => down_left
+///6 => left
+///7 => up_left
 #[derive(Component)]
 pub struct Direction {
     d: i32,
 }
 
 // combat
-#[derive(Component, Debug, Clone, Copy, PartialEq)]
-pub enum Turn {
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum TurnLabel {
     Player,
     Enemy,
 }
 
-#[derive(Component, Debug, Clone, Copy, PartialEq)]
-pub struct PlayerTurn(Turn);
+/// Physics collisions are turned into rule calls before anyone acts.
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum CombatLabel {
+    Collisions,
+}
 
 #[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Clone)]
-pub enum TurnLabel {
-    Player,
-    Enemy,
+pub enum MatchLabel {
+    Reset,
 }
 
-#[derive(Component, Debug, Clone, Copy, PartialEq)]
-struct Round {
-    count: i32,
+/// Everything belonging to the match being played, despawned when the next one starts.
+#[derive(Component)]
+pub struct MatchEntity;
+
+/// Gives systems access to the rules, publishing the resulting events and recording every
+/// call when a match is being recorded. The state is snapshotted for the autosave as each
+/// turn starts.
+#[derive(SystemParam)]
+pub struct Orders<'w, 's> {
+    pub state: ResMut<'w, GameState>,
+    game_events: EventWriter<'w, 's, GameEvent>,
+    recorder: Option<ResMut<'w, Recorder>>,
+    autosave: Option<ResMut<'w, Autosave>>,
 }
 
-#[derive(Component, Debug, Clone, Copy, PartialEq)]
-struct TargetReticule;
+impl<'w, 's> Orders<'w, 's> {
+    pub fn issue(&mut self, action: Action) {
+        let turn = self.state.turn_number;
+        let events = rules::apply_action(&mut self.state, action);
+        if !events.is_empty() {
+            self.record(Record::Action { turn, action });
+        }
+        self.publish(events);
+    }
+
+    pub fn damage(
+        &mut self,
+        target: ShipId,
+        amount: i32,
+        kind: DamageKind,
+        source: Option<ShipId>,
+    ) {
+        let turn = self.state.turn_number;
+        let events = rules::apply_damage(&mut self.state, target, amount, kind, source);
+        if !events.is_empty() {
+            self.record(Record::Damage {
+                turn,
+                target,
+                amount,
+                kind,
+                source,
+            });
+        }
+        self.publish(events);
+    }
+
+    pub fn miss(&mut self) {
+        let turn = self.state.turn_number;
+        let events = rules::apply_miss(&mut self.state);
+        self.record(Record::Miss { turn });
+        self.publish(events);
+    }
+
+    fn publish(&mut self, events: Vec<GameEvent>) {
+        let new_turn = events.iter().any(|event| {
+            matches!(
+                event,
+                GameEvent::PhaseStarted(Phase::PlayerInput | Phase::EnemyPlan)
+            )
+        });
+        if let Some(autosave) = self.autosave.as_mut().filter(|_| new_turn) {
+            autosave.0 = Some(self.state.clone());
+        }
+        self.game_events.send_batch(events.into_iter());
+    }
+
+    fn record(&mut self, record: Record) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.replay.records.push(record);
+        }
+    }
+}
 
 // collision
 #[derive(PhysicsLayer)]
-enum Layer {
+pub enum Layer {
     Player,
     Enemy,
     Rock,
     CannonBall,
 }
 
+/// Sprite handles, left empty when running headless.
+#[derive(Default)]
+pub struct Textures {
+    /// Ship sprites by path.
+    ships: HashMap<String, Handle<Image>>,
+    cannonball: Handle<Image>,
+    rocks: [Handle<Image>; 3],
+}
+
 // game
-#[derive(Component, Debug, Clone, Copy, PartialEq)]
-struct GameOverEvent;
+/// A cannonball fired from the ship entity `source` that the physics engine found hitting
+/// the ship entity `target`, on its way to the rules. Damage from rocks, ramming and fire is
+/// dealt inside the rules; watch for `GameEvent::Damaged` to see every hit, whatever its
+/// kind.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub source: Entity,
+    pub amount: i32,
+    pub kind: DamageKind,
+}
+
+/// A cannonball fired by the ship entity `owner` that hit nothing but a rock or the sea.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SplashEvent {
+    pub position: Vec2,
+    pub owner: Entity,
+}
+
+/// Sent once the match is decided, with what the end screen shows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameOverEvent {
+    pub outcome: Outcome,
+    pub rounds: u32,
+    pub stats: BattleStats,
+    pub player_health: i32,
+    pub enemy_health: i32,
+}
+
+fn load_textures(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    classes: Res<ShipClasses>,
+) {
+    commands.insert_resource(Textures {
+        ships: classes
+            .iter()
+            .flat_map(|class| [&class.sprites.player, &class.sprites.enemy])
+            .map(|path| (path.clone(), asset_server.load(path.as_str())))
+            .collect(),
+        cannonball: asset_server.load("textures/ship_parts/cannonBall.png"),
+        rocks: [
+            asset_server.load("textures/tiles/tile_49.png"),
+            asset_server.load("textures/tiles/tile_50.png"),
+            asset_server.load("textures/tiles/tile_51.png"),
+        ],
+    });
+}
 
 fn setup_camera(mut commands: Commands) {
     commands.spawn_bundle(OrthographicCameraBundle::new_2d());
 }
 
-fn setup_rocks(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let rocks: [Handle<Image>; 3] = [
-        asset_server.load("textures/tiles/tile_49.png"),
-        asset_server.load("textures/tiles/tile_50.png"),
-        asset_server.load("textures/tiles/tile_51.png"),
-    ];
+/// Despawns whatever is left of the previous match.
+fn clear_match(mut commands: Commands, leftovers: Query<Entity, With<MatchEntity>>) {
+    for entity in leftovers.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
 
-    let mut spawned_rocks = vec![];
+/// Seeds the map of the next match: from the replay being played back, from the options,
+/// or at random.
+fn reset_match(
+    mut rng: ResMut<GameRng>,
+    mut settings: ResMut<MatchSettings>,
+    mut pending: ResMut<ui::PendingGameOver>,
+    playback: Option<ResMut<Playback>>,
+    recorder: Option<ResMut<Recorder>>,
+    autosave: Option<ResMut<Autosave>>,
+) {
+    // Forget the outcome of a match quit while it was paused, and its last turn.
+    pending.0 = None;
+    if let Some(mut autosave) = autosave {
+        autosave.0 = None;
+    }
+    if let Some(mut playback) = playback {
+        playback.rewind();
+        *settings = playback.settings();
+    }
+    *rng = GameRng::new(settings.seed.unwrap_or_else(rand::random));
+    if let Some(mut recorder) = recorder {
+        let seed = rng.seed();
+        recorder.start(seed, settings.clone());
+    }
+    println!("seed: {}", rng.seed());
+}
+
+/// Sets up the battle, either restored from `--load` or freshly generated. Ships are
+/// spawned in a fixed order so their `ShipId`s match between a recording and its playback.
+fn start_match(
+    mut commands: Commands,
+    mut state: ResMut<GameState>,
+    mut rng: ResMut<GameRng>,
+    textures: Res<Textures>,
+    classes: Res<ShipClasses>,
+    settings: Res<MatchSettings>,
+    saved: Option<Res<SavedBattle>>,
+) {
+    if let Some(saved) = saved {
+        save::restore_battle(&mut commands, &saved, &state, &textures, &classes);
+        commands.remove_resource::<SavedBattle>();
+        return;
+    }
+
+    *state = GameState::new(BOUNDS, MAX_ROUNDS);
+    state.friendly_fire = settings.friendly_fire;
+    setup_rocks(&mut commands, &mut state, &mut rng, &textures);
+    forecast_wind(&mut state, &mut rng);
+    let player_class = classes
+        .get(&settings.ship_class)
+        .expect("ship classes are checked at startup");
+    spawn_player_ship(&mut commands, &mut state, &textures, player_class);
+    let enemy_class = settings.difficulty.enemy_class(&classes);
+    spawn_enemy_ships(&mut commands, &mut state, &textures, enemy_class, &settings);
+}
+
+/// Forecasts the wind of every round, veering and gusting a little from one to the next.
+fn forecast_wind(state: &mut GameState, rng: &mut GameRng) {
+    let mut wind = Wind {
+        direction: rng.gen_range(0, 8),
+        strength: rng.gen_range(0.3, 1.0),
+    };
+    for _ in 0..state.round.max {
+        state.winds.push(wind);
+        wind.direction = (wind.direction + rng.gen_range(-1, 2)).rem_euclid(8);
+        wind.strength = (wind.strength + rng.gen_range(-0.2, 0.2)).clamp(0.1, 1.0);
+    }
+}
+
+fn setup_rocks(
+    commands: &mut Commands,
+    state: &mut GameState,
+    rng: &mut GameRng,
+    textures: &Textures,
+) {
+    let mut spawned_rocks: Vec<(f32, f32)> = vec![];
 
     for _ in 0..3 {
-        let rock_type: usize = rand::thread_rng().gen_range(0, rocks.len());
-        let mut rock_x: f32 = rand::thread_rng().gen_range(
-            (-(WINDOW_WIDTH as f32) / 2.0) + 200.0,
-            ((WINDOW_WIDTH as f32) / 2.0) - 200.0,
-        );
-        let mut rock_y: f32 = rand::thread_rng().gen_range(
-            (-(WINDOW_HEIGHT as f32) / 2.0) + 100.0,
-            ((WINDOW_HEIGHT as f32) / 2.0) - 100.0,
+        let rock_type: usize = rng.gen_range(0, textures.rocks.len());
+        let mut rock_x: f32 =
+            rng.gen_range((-WINDOW_WIDTH / 2.0) + 200.0, (WINDOW_WIDTH / 2.0) - 200.0);
+        let mut rock_y: f32 = rng.gen_range(
+            (-WINDOW_HEIGHT / 2.0) + 100.0,
+            (WINDOW_HEIGHT / 2.0) - 100.0,
         );
-        let rock_rot: f32 = rand::thread_rng().gen_range(0.0, 360.0);
-        let rock_size: f32 = rand::thread_rng().gen_range(0.4, 1.1);
+        let rock_rot: f32 = rng.gen_range(0.0, 360.0);
+        let rock_size: f32 = rng.gen_range(0.4, 1.1);
 
         // make sure rocks are spaced apart
-        if spawned_rocks.len() > 0 {
-            for j in 0..spawned_rocks.len() {
-                let spawned_tmp: (f32, f32) = spawned_rocks[j];
-                let spawned_x: f32 = spawned_tmp.0;
-                let spawned_y: f32 = spawned_tmp.1;
-
-                while (rock_x >= spawned_x - 60.0 && rock_x <= spawned_x + 60.0)
-                    && (rock_y >= spawned_y - 60.0 && rock_y <= spawned_y + 60.0)
-                {
-                    rock_x = rand::thread_rng().gen_range(
-                        (-(WINDOW_WIDTH as f32) / 2.0) + 100.0,
-                        ((WINDOW_WIDTH as f32) / 2.0) - 100.0,
-                    );
-                    rock_y = rand::thread_rng().gen_range(
-                        (-(WINDOW_HEIGHT as f32) / 2.0) + 100.0,
-                        ((WINDOW_HEIGHT as f32) / 2.0) - 100.0,
-                    );
-                }
+        for &(spawned_x, spawned_y) in spawned_rocks.iter() {
+            while (rock_x >= spawned_x - 60.0 && rock_x <= spawned_x + 60.0)
+                && (rock_y >= spawned_y - 60.0 && rock_y <= spawned_y + 60.0)
+            {
+                rock_x = rng.gen_range((-WINDOW_WIDTH / 2.0) + 100.0, (WINDOW_WIDTH / 2.0) - 100.0);
+                rock_y = rng.gen_range(
+                    (-WINDOW_HEIGHT / 2.0) + 100.0,
+                    (WINDOW_HEIGHT / 2.0) - 100.0,
+                );
             }
-            spawned_rocks.push((rock_x, rock_y));
-        } else {
-            spawned_rocks.push((rock_x, rock_y));
         }
+        spawned_rocks.push((rock_x, rock_y));
 
-        commands
-            .spawn_bundle(SpriteBundle {
-                texture: rocks[rock_type].clone(),
-                transform: Transform {
-                    scale: Vec3::new(2.0, 2.0, 2.0),
-                    rotation: Quat::from_rotation_z(f32::to_radians(rock_rot)),
-                    translation: Vec3::new(rock_x, rock_y, 0.0),
-                    ..Default::default()
-                },
-                ..Default::default()
-            })
-            .insert(RigidBody::Static)
-            .insert(CollisionShape::Sphere {
-                radius: rock_size * 13.0,
-            })
-            .insert(
-                CollisionLayers::none()
-                    .with_group(Layer::Rock)
-                    .with_masks(&[Layer::Enemy, Layer::Player, Layer::CannonBall]),
-            );
+        let rock = Rock {
+            position: Vec2::new(rock_x, rock_y),
+            radius: rock_size * 13.0,
+            rotation: f32::to_radians(rock_rot),
+            variant: rock_type,
+        };
+        state.add_rock(rock);
+        spawn_rock(commands, textures, &rock);
+    }
+}
+
+fn spawn_player_ship(
+    commands: &mut Commands,
+    state: &mut GameState,
+    textures: &Textures,
+    class: &ShipClass,
+) {
+    let ship = state.add_ship(Ship::new(
+        Side::Player,
+        &class.name,
+        class.hull,
+        Vec2::new(WINDOW_WIDTH - 500.0, -WINDOW_HEIGHT + 500.0),
+        0,
+    ));
+    spawn_ship(commands, textures, class, ship, state.ship(ship));
+}
+
+/// Sails the enemy fleet in echelon from the flagship's position.
+fn spawn_enemy_ships(
+    commands: &mut Commands,
+    state: &mut GameState,
+    textures: &Textures,
+    class: &ShipClass,
+    settings: &MatchSettings,
+) {
+    let flagship = Vec2::new(-WINDOW_WIDTH + 500.0, WINDOW_HEIGHT - 500.0);
+    for index in 0..settings.fleet_size {
+        let ship = state.add_ship(Ship::new(
+            Side::Enemy,
+            &class.name,
+            class.hull,
+            flagship + echelon_offset(index),
+            4,
+        ));
+        spawn_ship(commands, textures, class, ship, state.ship(ship));
     }
 }
 
-fn spawn_player_ship(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let player_ship = asset_server.load("textures/ships/ship (10).png");
-    // let ship_rot: i32 = rand::thread_rng().gen_range(-90, 1);
+/// Where the enemy ship with the given index starts, relative to the flagship. Ships after the
+/// flagship take turns falling in on its right and left, one step further out each pair.
+fn echelon_offset(index: usize) -> Vec2 {
+    let step = index.div_ceil(2) as f32;
+    match index % 2 {
+        0 => ECHELON_LEFT * step,
+        _ => ECHELON_RIGHT * step,
+    }
+}
 
+pub fn spawn_rock(commands: &mut Commands, textures: &Textures, rock: &Rock) {
     commands
         .spawn_bundle(SpriteBundle {
-            texture: player_ship,
+            texture: textures.rocks[rock.variant].clone(),
             transform: Transform {
-                scale: Vec3::new(0.75, 0.75, 0.75),
-                translation: Vec3::new(
-                    (WINDOW_WIDTH as f32) - 500.0,
-                    -(WINDOW_HEIGHT as f32) + 500.0,
-                    0.0,
-                ),
-                // rotation: Quat::from_rotation_z(f32::to_radians(ship_rot)),
-                ..Default::default()
+                scale: Vec3::new(2.0, 2.0, 2.0),
+                rotation: Quat::from_rotation_z(rock.rotation),
+                translation: rock.position.extend(0.0),
             },
             ..Default::default()
         })
-        .insert(Player)
-        .insert(Health { value: 3 })
-        .insert(ActionPoints { value: 0 })
-        .insert(Direction { d: 0 })
+        .insert(MatchEntity)
         .insert(RigidBody::Static)
         .insert(CollisionShape::Sphere {
-            radius: SHIP_SIZE * 100.0,
+            radius: rock.radius,
         })
         .insert(
             CollisionLayers::none()
-                .with_group(Layer::Player)
-                .with_masks(&[Layer::Enemy, Layer::Rock, Layer::CannonBall]),
+                .with_group(Layer::Rock)
+                .with_masks(&[Layer::Enemy, Layer::Player, Layer::CannonBall]),
         );
 }
 
-fn spawn_enemy_ships(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let enemy_ship = asset_server.load("textures/ships/ship (8).png");
-    // let ship_rot: i32 = rand::thread_rng().gen_range(90, 181);
+/// Spawns the entity mirroring `ship`, which must already be part of the `GameState`.
+pub fn spawn_ship(
+    commands: &mut Commands,
+    textures: &Textures,
+    class: &ShipClass,
+    id: ShipId,
+    ship: &Ship,
+) -> Entity {
+    let (sprite, group, opponent) = match ship.side {
+        Side::Player => (&class.sprites.player, Layer::Player, Layer::Enemy),
+        Side::Enemy => (&class.sprites.enemy, Layer::Enemy, Layer::Player),
+    };
 
+    let mut entity = commands.spawn_bundle(SpriteBundle {
+        texture: textures.ships.get(sprite).cloned().unwrap_or_default(),
+        transform: Transform {
+            scale: Vec3::splat(class.scale),
+            translation: ship.position.extend(0.0),
+            rotation: Quat::from_rotation_z(ship.rotation),
+        },
+        ..Default::default()
+    });
+    entity
+        .insert(MatchEntity)
+        .insert(id)
+        .insert(Health { value: ship.health })
+        .insert(Sails { value: ship.sails })
+        .insert(Rudder { value: ship.rudder })
+        .insert(Crew { value: ship.crew })
+        .insert(ActionPoints {
+            value: ship.action_points,
+        })
+        .insert(Direction { d: ship.direction })
+        .insert(RigidBody::Static)
+        .insert(CollisionShape::Sphere {
+            radius: ship.hull.radius,
+        })
+        .insert(CollisionLayers::none().with_group(group).with_masks(&[
+            opponent,
+            Layer::Rock,
+            Layer::CannonBall,
+        ]));
+    match ship.side {
+        Side::Player => entity.insert(Player),
+        Side::Enemy => entity.insert(Enemy).insert(enemy_ai::Brain::default()),
+    };
+    entity.id()
+}
+
+/// Spawns the entity drawing `wreck`, the remains of a ship of `class` and `side`.
+pub fn spawn_wreck(
+    commands: &mut Commands,
+    textures: &Textures,
+    class: &ShipClass,
+    side: Side,
+    wreck: &Wreck,
+) -> Entity {
+    let sprite = match side {
+        Side::Player => &class.sprites.player,
+        Side::Enemy => &class.sprites.enemy,
+    };
     commands
         .spawn_bundle(SpriteBundle {
-            texture: enemy_ship,
+            texture: textures.ships.get(sprite).cloned().unwrap_or_default(),
+            sprite: Sprite {
+                color: Color::rgba(0.3, 0.25, 0.2, 0.8),
+                ..Default::default()
+            },
+            transform: Transform {
+                scale: Vec3::splat(class.scale * wreck.radius / class.hull.radius),
+                // below the ships sailing past
+                translation: wreck.position.extend(-0.5),
+                rotation: Quat::from_rotation_z(wreck.rotation),
+            },
+            ..Default::default()
+        })
+        .insert(MatchEntity)
+        .insert(Wreckage)
+        .id()
+}
+
+/// Spawns a cannonball fired by the `owner` ship of `side`, which can only hit the other
+/// side's ships unless `friendly_fire` is on. It splashes down at the end of its `range`.
+/// The ball is a sensor: contacts are reported but never stop it, so a ship sailing into
+/// its path cannot leave it hanging in the water.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_cannonball(
+    commands: &mut Commands,
+    textures: &Textures,
+    owner: Entity,
+    ammo: Ammo,
+    side: Side,
+    friendly_fire: bool,
+    position: Vec2,
+    velocity: Vec2,
+    range: Range,
+) {
+    let (own, target) = match side {
+        Side::Player => (Layer::Player, Layer::Enemy),
+        Side::Enemy => (Layer::Enemy, Layer::Player),
+    };
+    let layers = CollisionLayers::none().with_group(Layer::CannonBall);
+    let layers = if friendly_fire {
+        layers.with_masks(&[Layer::Rock, target, own])
+    } else {
+        layers.with_masks(&[Layer::Rock, target])
+    };
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            texture: textures.cannonball.clone(),
             transform: Transform {
                 scale: Vec3::new(0.75, 0.75, 0.75),
-                translation: Vec3::new(
-                    -(WINDOW_WIDTH as f32) + 500.0,
-                    (WINDOW_HEIGHT as f32) - 500.0,
-                    0.0,
-                ),
-                rotation: Quat::from_rotation_z(f32::to_radians(180.0)),
+                translation: position.extend(0.0),
                 ..Default::default()
             },
             ..Default::default()
         })
-        .insert(Enemy)
-        .insert(Direction { d: 4 })
-        .insert(Health { value: 5 })
-        .insert(ActionPoints { value: 0 })
-        .insert(RigidBody::Static)
+        .insert(CannonBall)
+        .insert(Owner(owner))
+        .insert(ammo)
+        .insert(range)
+        .insert(MatchEntity)
+        .insert(RigidBody::Dynamic)
         .insert(CollisionShape::Sphere {
-            radius: SHIP_SIZE * 100.0,
+            radius: CANNONBALL_RADIUS,
         })
-        .insert(
-            CollisionLayers::none()
-                .with_group(Layer::Enemy)
-                .with_masks(&[Layer::Player, Layer::Rock, Layer::CannonBall]),
-        );
+        .insert(SensorShape)
+        .insert(layers)
+        .insert(Velocity::from_linear(velocity.extend(0.0)));
+}
+
+/// Keys that act once per press, held from the frame they were pressed until the next
+/// `ship_movement` tick.
+#[derive(Default)]
+struct KeyPresses {
+    load: Option<Ammo>,
+    surrender: bool,
 }
 
-pub fn get_gun_arc(d: i32) -> Vec3 {
-    match d {
-        0 => Vec3::new(0.0, 1.0, 0.0),
-        1 => Vec3::new(1.0, 1.0, 0.0),
-        2 => Vec3::new(1.0, 0.0, 0.0),
-        3 => Vec3::new(1.0, -1.0, 0.0),
-        4 => Vec3::new(0.0, -1.0, 0.0),
-        5 => Vec3::new(-1.0, -1.0, 0.0),
-        6 => Vec3::new(-1.0, 0.0, 0.0),
-        7 => Vec3::new(-1.0, 1.0, 0.0),
-        _ => Vec3::new(0.0, 0.0, 0.0),
+/// Latches the keys that act once per press. It runs every frame, as a press only shows
+/// for one and `ship_movement` runs on a fixed timestep.
+fn latch_key_presses(
+    mut presses: ResMut<KeyPresses>,
+    app_state: Res<State<AppState>>,
+    keyboard_input: Res<Input<KeyCode>>,
+) {
+    if app_state.current() != &AppState::InGame {
+        *presses = KeyPresses::default();
+        return;
+    }
+    let loaded = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4]
+        .iter()
+        .zip(Ammo::ALL)
+        .find(|(&key, _)| keyboard_input.just_pressed(key));
+    if let Some((_, ammo)) = loaded {
+        presses.load = Some(ammo);
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        presses.surrender = true;
     }
 }
 
+/// Turns the player's key presses into rule actions.
 fn ship_movement(
-    mut commands: Commands,
-    mut player_turn: ResMut<PlayerTurn>,
+    mut orders: Orders,
+    mut presses: ResMut<KeyPresses>,
+    app_state: Res<State<AppState>>,
     keyboard_input: Res<Input<KeyCode>>,
-    asset_server: Res<AssetServer>,
-    mut player: Query<(
-        With<Player>,
+    player: Query<&ShipId, With<Player>>,
+) {
+    if app_state.current() != &AppState::InGame {
+        return;
+    }
+    // a press while the ship can't act is dropped rather than saved for its turn
+    let loaded = presses.load.take();
+    let surrender = std::mem::take(&mut presses.surrender);
+    for &ship in player.iter() {
+        let action = if surrender {
+            Action::Surrender(Side::Player)
+        } else if !orders.state.can_act(ship) {
+            // stunned, or not our turn
+            continue;
+        } else if let Some(ammo) = loaded {
+            Action::Load { ship, ammo }
+        } else if keyboard_input.pressed(KeyCode::Space) {
+            Action::Fire {
+                ship,
+                aim: Aim::Broadside,
+            }
+        } else if keyboard_input.pressed(KeyCode::A) {
+            Action::Steer {
+                ship,
+                steer: Steer::Left,
+            }
+        } else if keyboard_input.pressed(KeyCode::D) {
+            Action::Steer {
+                ship,
+                steer: Steer::Right,
+            }
+        } else if keyboard_input.pressed(KeyCode::W) {
+            Action::Steer {
+                ship,
+                steer: Steer::Forward,
+            }
+        } else {
+            continue;
+        };
+
+        orders.issue(action);
+    }
+}
+
+/// Mirrors the rules state onto the ship entities.
+#[allow(clippy::type_complexity)]
+fn sync_ships(
+    state: Res<GameState>,
+    mut ships: Query<(
+        &ShipId,
         &mut Transform,
         &mut Direction,
+        &mut Health,
+        &mut Sails,
+        &mut Rudder,
+        &mut Crew,
         &mut ActionPoints,
     )>,
 ) {
-    for (_, mut transform, mut direction, mut ap) in player.iter_mut() {
-        if Turn::Player == player_turn.0 {
-            let mut rotation_factor = 0.0;
-            let mut movement_factor = 0.0;
-
-            if keyboard_input.pressed(KeyCode::Space) {
-                if ap.value == 3 {
-                    let mut l_dir = direction.d - 2;
-                    if l_dir < 0 {
-                        l_dir = direction.d + 8 - 2;
-                    }
-                    let mut r_dir = direction.d + 2;
-                    if r_dir > 7 {
-                        r_dir = direction.d - 8 + 2;
-                    }
-
-                    let left = get_gun_arc(l_dir);
-                    let right = get_gun_arc(r_dir);
-
-                    //Handle direction to rotatio        t.translation.x += 10.0;
-                    commands
-                        .spawn_bundle(SpriteBundle {
-                            texture: asset_server.load("textures/ship_parts/cannonBall.png"),
-                            transform: transform.clone(),
-                            ..Default::default()
-                        })
-                        .insert(RigidBody::Dynamic)
-                        .insert(CollisionShape::Sphere { radius: 10.0 })
-                        .insert(
-                            CollisionLayers::none()
-                                .with_group(crate::Layer::CannonBall)
-                                .with_masks(&[crate::Layer::Rock, crate::Layer::Enemy]),
-                        )
-                        .insert(Velocity::from_linear(left * 1000.0));
-
-                    commands
-                        .spawn_bundle(SpriteBundle {
-                            texture: asset_server.load("textures/ship_parts/cannonBall.png"),
-                            transform: transform.clone(),
-                            ..Default::default()
-                        })
-                        .insert(RigidBody::Dynamic)
-                        .insert(CollisionShape::Sphere { radius: 10.0 })
-                        .insert(
-                            CollisionLayers::none()
-                                .with_group(crate::Layer::CannonBall)
-                                .with_masks(&[crate::Layer::Rock, crate::Layer::Enemy]),
-                        )
-                        .insert(Velocity::from_linear(right * 1000.0));
-
-                    ap.value -= 3;
-                }
-            } else {
-                // rotate on left/right
-                if keyboard_input.pressed(KeyCode::A) {
-                    if direction.d == 0 {
-                        direction.d = 7;
-                    } else {
-                        direction.d -= 1;
-                    }
-                    movement_factor += FORWARD_MOVE_DIST;
-                    rotation_factor += 1.0;
-
-                    if ap.value < 3 {
-                        ap.value += 1;
-                    }
-
-                    player_turn.0 = Turn::Enemy;
-                }
-                if keyboard_input.pressed(KeyCode::D) {
-                    if direction.d == 7 {
-                        direction.d = 0;
-                    } else {
-                        direction.d += 1;
-                    }
-                    movement_factor += FORWARD_MOVE_DIST;
-                    rotation_factor -= 1.0;
-
-                    if ap.value < 3 {
-                        ap.value += 1;
-                    }
-
-                    player_turn.0 = Turn::Enemy;
-                }
-
-                // move forward
-                if keyboard_input.pressed(KeyCode::W) {
-                    movement_factor += FORWARD_MOVE_DIST;
-                    if ap.value < 3 {
-                        ap.value += 1;
-                    }
-                    player_turn.0 = Turn::Enemy;
-                }
-            }
+    if !state.is_changed() {
+        return;
+    }
 
-            for _ in 0..2 {
-                let rotation_delta = Quat::from_rotation_z(rotation_factor * f32::to_radians(22.5));
+    for (&id, mut transform, mut direction, mut health, mut sails, mut rudder, mut crew, mut ap) in
+        ships.iter_mut()
+    {
+        let ship = state.ship(id);
+        transform.translation = ship.position.extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(ship.rotation);
+        direction.d = ship.direction;
+        health.value = ship.health;
+        sails.value = ship.sails;
+        rudder.value = ship.rudder;
+        crew.value = ship.crew;
+        ap.value = ship.action_points;
+    }
+}
 
-                // move and rotate
-                let movement_direction = transform.rotation * Vec3::Y;
-                let movement_distance = movement_factor * 1.0;
-                let translation_delta = movement_direction * movement_distance;
-                transform.translation += translation_delta;
-                transform.rotation *= rotation_delta;
-            }
+/// Sends every ship that went down under the waves: it stops colliding, shrinks and fades
+/// away in a splash, leaving its wreck behind.
+fn sink_ships(
+    mut commands: Commands,
+    mut game_events: EventReader<GameEvent>,
+    state: Res<GameState>,
+    textures: Res<Textures>,
+    classes: Res<ShipClasses>,
+    ships: Query<(Entity, &ShipId, &Transform)>,
+) {
+    for event in game_events.iter() {
+        let ship = match event {
+            GameEvent::Sunk { ship } => *ship,
+            _ => continue,
+        };
+        let (entity, transform) = match ships.iter().find(|(_, &id, _)| id == ship) {
+            Some((entity, _, transform)) => (entity, transform),
+            None => continue,
+        };
+        let sunk = state.ship(ship);
+        let class = classes
+            .get(&sunk.class)
+            .expect("ship classes are checked at startup");
+        commands
+            .entity(entity)
+            .remove::<RigidBody>()
+            .remove::<CollisionShape>()
+            .insert(Fading {
+                timer: Timer::from_seconds(SINKING_SECONDS, false),
+                from: transform.scale.x,
+                to: transform.scale.x * 0.3,
+            });
+        if let Some(wreck) = state.wrecks.iter().find(|wreck| wreck.ship == ship) {
+            spawn_wreck(&mut commands, &textures, class, sunk.side, wreck);
+        }
+        commands
+            .spawn_bundle(SpriteBundle {
+                texture: textures.cannonball.clone(),
+                sprite: Sprite {
+                    color: Color::rgba(0.9, 0.95, 1.0, 0.8),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(sunk.position.extend(0.5)),
+                ..Default::default()
+            })
+            .insert(MatchEntity)
+            .insert(Fading {
+                timer: Timer::from_seconds(SPLASH_SECONDS, false),
+                from: 1.0,
+                to: 4.0,
+            });
+    }
+}
 
-            // map boundaries
-            let extents = Vec3::from((BOUNDS / 2.0, 0.0));
-            transform.translation = transform.translation.min(extents).max(-extents);
+/// Plays out every `Fading`, on the physics clock so pausing holds it.
+fn fade_out(
+    mut commands: Commands,
+    time: Res<Time>,
+    physics_time: Res<PhysicsTime>,
+    mut fading: Query<(Entity, &mut Fading, &mut Transform, &mut Sprite)>,
+) {
+    let delta = time.delta().mul_f32(physics_time.scale());
+    for (entity, mut fading, mut transform, mut sprite) in fading.iter_mut() {
+        if fading.timer.tick(delta).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let progress = fading.timer.percent();
+        transform.scale = Vec3::splat(fading.from + (fading.to - fading.from) * progress);
+        sprite.color.set_a(1.0 - progress);
+    }
+}
+
+/// Launches the balls of every volley fired. Each ball drops into the sea at the ship's gun
+/// range.
+fn spawn_cannonballs(
+    mut commands: Commands,
+    mut game_events: EventReader<GameEvent>,
+    state: Res<GameState>,
+    textures: Res<Textures>,
+    ships: Query<(Entity, &ShipId)>,
+) {
+    for event in game_events.iter() {
+        if let GameEvent::Fired { ship, ammo, shots } = event {
+            let owner = match ships.iter().find(|(_, id)| *id == ship) {
+                Some((entity, _)) => entity,
+                None => continue,
+            };
+            let firing = state.ship(*ship);
+            let flight = (firing.hull.guns.range - firing.hull.guns.muzzle_offset).max(0.0);
+            for shot in shots {
+                spawn_cannonball(
+                    &mut commands,
+                    &textures,
+                    owner,
+                    *ammo,
+                    firing.side,
+                    state.friendly_fire,
+                    shot.origin,
+                    shot.direction * CANNONBALL_SPEED,
+                    Range {
+                        origin: shot.origin,
+                        distance: flight,
+                        time_left: flight / CANNONBALL_SPEED + RANGE_SLACK_SECONDS,
+                    },
+                );
+            }
         }
     }
 }
 
-fn ship_collision(
+/// Reports cannonballs hitting a ship to the rules, and splashes those hitting a rock.
+/// During playback the recorded hits are applied instead and the cannonballs are only
+/// cleaned up.
+#[allow(clippy::too_many_arguments)]
+fn cannon_fodder(
+    mut commands: Commands,
     mut events: EventReader<CollisionEvent>,
-    mut query: QuerySet<(
-        QueryState<&mut Health, With<Player>>,
-        QueryState<&mut Health, With<Enemy>>,
-    )>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut splash_events: EventWriter<SplashEvent>,
+    playback: Option<Res<Playback>>,
+    state: Res<GameState>,
+    ships: Query<&ShipId>,
+    cannonballs: Query<(&Owner, &Ammo, &Transform, &Velocity)>,
 ) {
-    events.iter().filter(|e| e.is_started()).for_each(|event| {
+    for event in events.iter().filter(|e| e.is_started()) {
         let (layers_1, layers_2) = event.collision_layers();
         let (entity_1, entity_2) = event.rigid_body_entities();
 
-        if (is_player(layers_1) && is_enemy(layers_2))
-            || (is_player(layers_2) && is_enemy(layers_1))
-        {
-            println!("Collision between ships");
-            for mut health in query.q0().iter_mut() {
-                health.value -= 1;
-                println!("Player health: {}", health.value); // DEBUG!
-            }
-            for mut health in query.q1().iter_mut() {
-                health.value -= 1;
-                println!("Enemy health: {}", health.value); // DEBUG!
+        let (other_layers, other_entity, cannon_entity) = if is_cannonball(layers_2) {
+            (layers_1, entity_1, entity_2)
+        } else if is_cannonball(layers_1) {
+            (layers_2, entity_2, entity_1)
+        } else {
+            continue;
+        };
+        let (owner, ammo, position, travel) = match cannonballs.get(cannon_entity) {
+            Ok((&Owner(owner), &ammo, transform, velocity)) => (
+                owner,
+                ammo,
+                transform.translation.truncate(),
+                velocity.linear.truncate(),
+            ),
+            Err(_) => continue,
+        };
+
+        if is_rock(other_layers) {
+            splash_events.send(SplashEvent { position, owner });
+            commands.entity(cannon_entity).despawn();
+            continue;
+        }
+        if !is_ship(other_layers) {
+            continue;
+        }
+        let ship_entity = other_entity;
+        // With friendly fire on a ball can graze the ship that fired it, which counts as a
+        // miss.
+        if owner == ship_entity {
+            splash_events.send(SplashEvent { position, owner });
+            commands.entity(cannon_entity).despawn();
+            continue;
+        }
+        if let Ok(&ship) = ships.get(ship_entity) {
+            if playback.is_none() {
+                let quarter = state.ship(ship).quarter(travel);
+                damage_events.send(DamageEvent {
+                    target: ship_entity,
+                    source: owner,
+                    amount: ammo.damage(),
+                    kind: DamageKind::Cannonball(ammo, quarter),
+                });
             }
+            commands.entity(cannon_entity).despawn();
         }
+    }
+}
+
+/// Hands every `DamageEvent` to the rules. This is the only place damage found by the
+/// physics engine reaches the `GameState`.
+fn apply_damage(
+    mut damage_events: EventReader<DamageEvent>,
+    mut orders: Orders,
+    ships: Query<&ShipId>,
+) {
+    for event in damage_events.iter() {
+        if let Ok(&ship) = ships.get(event.target) {
+            let source = ships.get(event.source).ok().copied();
+            orders.damage(ship, event.amount, event.kind, source);
+        }
+    }
+}
 
-        if (is_player(layers_1) && is_rock(layers_2)) || (is_player(layers_2) && is_rock(layers_1))
+/// Splashes cannonballs that reached the end of their range or left the map. Range is
+/// measured by distance flown, so it holds however fast the physics is stepped, with the
+/// time a ball has been flying on the physics clock as a backstop.
+fn lost_cannonballs(
+    mut commands: Commands,
+    mut splash_events: EventWriter<SplashEvent>,
+    time: Res<Time>,
+    physics_time: Res<PhysicsTime>,
+    physics_steps: Res<PhysicsSteps>,
+    mut cannonballs: Query<(Entity, &Transform, &Owner, &mut Range)>,
+) {
+    let delta = if physics_steps.is_step_frame() {
+        physics_steps.duration().exact(time.delta()).as_secs_f32() * physics_time.scale()
+    } else {
+        0.0
+    };
+    let extents = BOUNDS / 2.0;
+    for (cannon_entity, transform, &Owner(owner), mut range) in cannonballs.iter_mut() {
+        range.time_left -= delta;
+        let position = transform.translation.truncate();
+        if position.distance(range.origin) >= range.distance
+            || range.time_left <= 0.0
+            || position.abs().cmpgt(extents).any()
         {
-            println!("Collision between player and rock");
-            for mut health in query.q0().iter_mut() {
-                health.value -= 1;
-                println!("Player health: {}", health.value); // DEBUG!
-            }
+            splash_events.send(SplashEvent { position, owner });
+            commands.entity(cannon_entity).despawn();
         }
+    }
+}
 
-        if (is_enemy(layers_1) && is_rock(layers_2)) || (is_enemy(layers_2) && is_rock(layers_1)) {
-            println!("Collision between enemy and rock");
-            println!("Enemy is stunned!");
+/// Reports every splash to the rules as a miss. During playback the recorded misses are
+/// applied instead.
+fn apply_misses(
+    mut splash_events: EventReader<SplashEvent>,
+    mut orders: Orders,
+    playback: Option<Res<Playback>>,
+) {
+    for _ in splash_events.iter() {
+        if playback.is_none() {
+            orders.miss();
         }
-    });
+    }
 }
 
-fn cannon_fodder(
-    mut commands: Commands,
-    mut events: EventReader<CollisionEvent>,
-    mut queryset: QuerySet<(
-        QueryState<&mut Health, With<Player>>,
-        QueryState<&mut Health, With<Enemy>>,
-    )>,
+fn announce_game_over(
+    mut game_events: EventReader<GameEvent>,
+    mut game_over: EventWriter<GameOverEvent>,
+    state: Res<GameState>,
 ) {
-    events
-        .iter()
-        .filter(|e| e.is_started())
-        .filter_map(|event| {
-            let (layers_1, layers_2) = event.collision_layers();
-            let (entity_1, entity_2) = event.rigid_body_entities();
-
-            if is_player(layers_1) && is_cannonball(layers_2) {
-                println!("Player hit by cannon");
-                for mut health in queryset.q0().iter_mut() {
-                    health.value -= 1;
-                    println!("Player health: {}", health.value); // DEBUG!
-                }
-                return Some(entity_2);
-            } else if is_cannonball(layers_2) && is_player(layers_1) {
-                println!("Player hit by cannon");
-                for mut health in queryset.q0().iter_mut() {
-                    health.value -= 1;
-                    println!("Player health: {}", health.value); // DEBUG!
-                }
-                return Some(entity_1);
-            }
+    for event in game_events.iter() {
+        if let GameEvent::GameOver(outcome) = *event {
+            game_over.send(GameOverEvent {
+                outcome,
+                rounds: state.round.current,
+                stats: state.stats,
+                player_health: state.fleet_health(Side::Player),
+                enemy_health: state.fleet_health(Side::Enemy),
+            });
+        }
+    }
+}
 
-            if is_enemy(layers_1) && is_cannonball(layers_2) {
-                println!("Enemy hit by cannon");
-                for mut health in queryset.q1().iter_mut() {
-                    health.value -= 1;
-                    println!("Enemy health: {}", health.value); // DEBUG!
-                }
-                return Some(entity_2);
-            } else if is_cannonball(layers_2) && is_enemy(layers_1) {
-                println!("Enemy hit by cannon");
-                for mut health in queryset.q1().iter_mut() {
-                    health.value -= 1;
-                    println!("Enemy health: {}", health.value); // DEBUG!
-                }
-                return Some(entity_1);
-            } else {
-                return None;
-            }
-        })
-        .for_each(|cannon_entity| commands.entity(cannon_entity).despawn());
+fn log_game_events(mut game_events: EventReader<GameEvent>) {
+    for event in game_events.iter() {
+        info!("{:?}", event);
+    }
 }
 
 fn is_player(layers: CollisionLayers) -> bool {
@@ -526,10 +1224,8 @@ fn is_enemy(layers: CollisionLayers) -> bool {
     !layers.contains_group(Layer::Player) && layers.contains_group(Layer::Enemy)
 }
 
-fn is_rock(layers: CollisionLayers) -> bool {
-    !layers.contains_group(Layer::Player)
-        && !layers.contains_group(Layer::Enemy)
-        && layers.contains_group(Layer::Rock)
+fn is_ship(layers: CollisionLayers) -> bool {
+    is_player(layers) || is_enemy(layers)
 }
 
 fn is_cannonball(layers: CollisionLayers) -> bool {
@@ -537,3 +1233,7 @@ fn is_cannonball(layers: CollisionLayers) -> bool {
         && !layers.contains_group(Layer::Enemy)
         && layers.contains_group(Layer::CannonBall)
 }
+
+fn is_rock(layers: CollisionLayers) -> bool {
+    layers.contains_group(Layer::Rock)
+}
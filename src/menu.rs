@@ -0,0 +1,371 @@
+//! The main menu, the options screen where the next match is configured, and the pause
+//! menu shown over a battle.
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use heron::PhysicsTime;
+use serde::{Deserialize, Serialize};
+
+use crate::classes::{ShipClass, ShipClasses};
+use crate::ui::{HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON};
+use crate::AppState;
+
+/// Longest seed that always fits in a `u64`.
+const MAX_SEED_DIGITS: usize = 19;
+
+/// Most enemy ships a battle can be fought against.
+pub const MAX_FLEET_SIZE: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// Class of the enemy ships: the weakest class on Easy, the strongest on Hard and the
+    /// one halfway between on Normal.
+    pub fn enemy_class(self, classes: &ShipClasses) -> &ShipClass {
+        let rank = match self {
+            Difficulty::Easy => 0,
+            Difficulty::Normal => 1,
+            Difficulty::Hard => 2,
+        };
+        classes.ranked(rank, 3)
+    }
+
+    fn next(self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+}
+
+/// What the next match is played with. `seed: None` draws a random map every match.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchSettings {
+    pub difficulty: Difficulty,
+    pub seed: Option<u64>,
+    /// Class of the player's ship, the first one in the ship classes file by default.
+    pub ship_class: String,
+    /// Number of enemy ships, from 1 to [`MAX_FLEET_SIZE`].
+    pub fleet_size: usize,
+    /// Whether cannonballs can hit ships of the side that fired them.
+    pub friendly_fire: bool,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        MatchSettings {
+            difficulty: Difficulty::Normal,
+            seed: None,
+            ship_class: String::new(),
+            fleet_size: 1,
+            friendly_fire: false,
+        }
+    }
+}
+
+/// Marks the entities of the main menu, options screen and pause menu.
+#[derive(Component)]
+pub struct MenuScreen;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuButton {
+    NewGame,
+    Options,
+    Quit,
+    Difficulty,
+    ShipClass,
+    FleetSize,
+    FriendlyFire,
+    Back,
+    Resume,
+    Restart,
+    Settings,
+}
+
+/// Text showing the current value of an option.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionText {
+    Difficulty,
+    ShipClass,
+    FleetSize,
+    FriendlyFire,
+    Seed,
+}
+
+pub fn main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/FiraMono-Regular.ttf");
+    spawn_screen(&mut commands, Color::NONE, |parent| {
+        spawn_text(parent, &font, "Rusty Pirates", 60.0);
+        spawn_button(parent, &font, MenuButton::NewGame, "New Game", None);
+        spawn_button(parent, &font, MenuButton::Options, "Options", None);
+        spawn_button(parent, &font, MenuButton::Quit, "Quit", None);
+    });
+}
+
+pub fn options_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/FiraMono-Regular.ttf");
+    spawn_screen(&mut commands, Color::NONE, |parent| {
+        spawn_text(parent, &font, "Options", 60.0);
+        spawn_button(
+            parent,
+            &font,
+            MenuButton::Difficulty,
+            "",
+            Some(OptionText::Difficulty),
+        );
+        spawn_button(
+            parent,
+            &font,
+            MenuButton::ShipClass,
+            "",
+            Some(OptionText::ShipClass),
+        );
+        spawn_button(
+            parent,
+            &font,
+            MenuButton::FleetSize,
+            "",
+            Some(OptionText::FleetSize),
+        );
+        spawn_button(
+            parent,
+            &font,
+            MenuButton::FriendlyFire,
+            "",
+            Some(OptionText::FriendlyFire),
+        );
+        parent
+            .spawn_bundle(TextBundle {
+                style: Style {
+                    margin: Rect::all(Val::Px(10.0)),
+                    ..Default::default()
+                },
+                text: Text::with_section("", text_style(&font, 30.0), Default::default()),
+                ..Default::default()
+            })
+            .insert(OptionText::Seed);
+        spawn_text(
+            parent,
+            &font,
+            "Type a seed, or clear it for a random map",
+            16.0,
+        );
+        spawn_button(parent, &font, MenuButton::Back, "Back", None);
+    });
+}
+
+pub fn pause_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load("fonts/FiraMono-Regular.ttf");
+    spawn_screen(&mut commands, Color::rgba(0.0, 0.0, 0.0, 0.6), |parent| {
+        spawn_text(parent, &font, "Paused", 60.0);
+        spawn_button(parent, &font, MenuButton::Resume, "Resume", None);
+        spawn_button(parent, &font, MenuButton::Restart, "Restart", None);
+        spawn_button(parent, &font, MenuButton::Settings, "Settings", None);
+        spawn_button(parent, &font, MenuButton::Quit, "Quit", None);
+    });
+}
+
+fn spawn_screen(
+    commands: &mut Commands,
+    background: Color,
+    children: impl FnOnce(&mut ChildBuilder),
+) {
+    commands
+        .spawn_bundle(UiCameraBundle::default())
+        .insert(MenuScreen);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                // lay the lines out top to bottom
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: background.into(),
+            ..Default::default()
+        })
+        .insert(MenuScreen)
+        .with_children(children);
+}
+
+fn text_style(font: &Handle<Font>, font_size: f32) -> TextStyle {
+    TextStyle {
+        font: font.clone(),
+        font_size,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    }
+}
+
+fn spawn_text(parent: &mut ChildBuilder, font: &Handle<Font>, text: &str, font_size: f32) {
+    parent.spawn_bundle(TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(10.0)),
+            ..Default::default()
+        },
+        text: Text::with_section(text, text_style(font, font_size), Default::default()),
+        ..Default::default()
+    });
+}
+
+fn spawn_button(
+    parent: &mut ChildBuilder,
+    font: &Handle<Font>,
+    button: MenuButton,
+    label: &str,
+    option: Option<OptionText>,
+) {
+    parent
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(400.0), Val::Px(65.0)),
+                margin: Rect::all(Val::Px(10.0)),
+                // horizontally center child text
+                justify_content: JustifyContent::Center,
+                // vertically center child text
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: NORMAL_BUTTON.into(),
+            ..Default::default()
+        })
+        .insert(button)
+        .with_children(|parent| {
+            let mut text = parent.spawn_bundle(TextBundle {
+                text: Text::with_section(label, text_style(font, 30.0), Default::default()),
+                ..Default::default()
+            });
+            if let Some(option) = option {
+                text.insert(option);
+            }
+        });
+}
+
+#[allow(clippy::type_complexity)]
+pub fn button_system(
+    mut app_state: ResMut<State<AppState>>,
+    mut settings: ResMut<MatchSettings>,
+    classes: Res<ShipClasses>,
+    mut exit: EventWriter<AppExit>,
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor, &MenuButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut color, button) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = PRESSED_BUTTON.into();
+                // Overwriting, as Esc may have toggled the pause in the same frame.
+                match button {
+                    MenuButton::NewGame => app_state.overwrite_set(AppState::InGame).unwrap(),
+                    MenuButton::Options => app_state.overwrite_set(AppState::Options).unwrap(),
+                    MenuButton::Quit => exit.send(AppExit),
+                    MenuButton::Difficulty => settings.difficulty = settings.difficulty.next(),
+                    MenuButton::ShipClass => {
+                        settings.ship_class = classes.next(&settings.ship_class).name.clone()
+                    }
+                    MenuButton::FleetSize => {
+                        settings.fleet_size = settings.fleet_size % MAX_FLEET_SIZE + 1
+                    }
+                    MenuButton::FriendlyFire => settings.friendly_fire = !settings.friendly_fire,
+                    // The options screen is either on top of the pause menu or on its own.
+                    MenuButton::Back if app_state.inactives().is_empty() => {
+                        app_state.overwrite_set(AppState::MainMenu).unwrap()
+                    }
+                    MenuButton::Back | MenuButton::Resume => app_state.overwrite_pop().unwrap(),
+                    MenuButton::Restart => app_state.overwrite_replace(AppState::InGame).unwrap(),
+                    MenuButton::Settings => app_state.overwrite_push(AppState::Options).unwrap(),
+                }
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+/// Edits the seed with the digit keys and backspace.
+pub fn edit_seed(
+    mut typed: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut settings: ResMut<MatchSettings>,
+) {
+    let mut digits = settings
+        .seed
+        .map(|seed| seed.to_string())
+        .unwrap_or_default();
+    let before = digits.clone();
+    for event in typed.iter() {
+        if event.char.is_ascii_digit() && digits.len() < MAX_SEED_DIGITS {
+            digits.push(event.char);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        digits.pop();
+    }
+    if digits != before {
+        settings.seed = digits.parse().ok();
+    }
+}
+
+pub fn option_text_system(
+    settings: Res<MatchSettings>,
+    mut texts: Query<(&mut Text, &OptionText)>,
+) {
+    for (mut text, option) in texts.iter_mut() {
+        text.sections[0].value = match option {
+            OptionText::Difficulty => format!("Difficulty: {:?}", settings.difficulty),
+            OptionText::ShipClass => format!("Ship: {}", settings.ship_class),
+            OptionText::FleetSize => format!("Enemies: {}", settings.fleet_size),
+            OptionText::FriendlyFire if settings.friendly_fire => "Friendly fire: On".to_string(),
+            OptionText::FriendlyFire => "Friendly fire: Off".to_string(),
+            OptionText::Seed => match settings.seed {
+                Some(seed) => format!("Seed: {}", seed),
+                None => "Seed: random".to_string(),
+            },
+        };
+    }
+}
+
+pub fn clear_menu(mut commands: Commands, menu: Query<Entity, With<MenuScreen>>) {
+    for entity in menu.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Esc pauses the battle, and resumes it from the pause menu.
+pub fn toggle_pause(
+    mut app_state: ResMut<State<AppState>>,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    // Overwriting, as the match may have ended in the same frame.
+    match app_state.current() {
+        AppState::InGame => app_state.overwrite_push(AppState::Paused).unwrap(),
+        AppState::Paused => app_state.overwrite_pop().unwrap(),
+        _ => (),
+    }
+    // Pressed once, handled once.
+    keyboard_input.clear_just_pressed(KeyCode::Escape);
+}
+
+pub fn pause_physics(mut physics_time: ResMut<PhysicsTime>) {
+    physics_time.pause();
+}
+
+pub fn resume_physics(mut physics_time: ResMut<PhysicsTime>) {
+    physics_time.resume();
+}
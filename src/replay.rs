@@ -0,0 +1,174 @@
+//! Match recording (`--record FILE`) and playback (`--replay FILE`).
+//!
+//! A replay is the map seed and match settings plus every rule call made during the match,
+//! in order. Cannonball hits and misses are recorded alongside the actions because they come
+//! from the physics engine, so playing back only the actions would not be guaranteed to land
+//! the same shots.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::menu::MatchSettings;
+use crate::rules::{Action, DamageKind, ShipId};
+use crate::{AppState, Orders};
+
+/// Bumped whenever the file format changes.
+pub const REPLAY_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Record {
+    Action {
+        turn: u32,
+        action: Action,
+    },
+    Damage {
+        turn: u32,
+        target: ShipId,
+        amount: i32,
+        kind: DamageKind,
+        source: Option<ShipId>,
+    },
+    Miss {
+        turn: u32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    /// The options the match was set up with, which decide the ships it starts with.
+    pub settings: MatchSettings,
+    pub records: Vec<Record>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, settings: MatchSettings) -> Self {
+        Replay {
+            version: REPLAY_VERSION,
+            seed,
+            settings,
+            records: Vec::new(),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let replay: Replay = ron::from_str(&fs::read_to_string(path)?)?;
+        if replay.version != REPLAY_VERSION {
+            return Err(format!("unsupported replay version {}", replay.version).into());
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+/// The match being recorded and the file it is written to. The first match of a session is
+/// written to the `--record` path itself, later ones to numbered files next to it, e.g.
+/// `match-2.ron`, so playing again doesn't overwrite earlier recordings.
+pub struct Recorder {
+    /// The `--record` path.
+    pub base: PathBuf,
+    pub path: PathBuf,
+    /// Matches recorded so far, the current one included.
+    pub matches: u32,
+    pub replay: Replay,
+}
+
+impl Recorder {
+    pub fn new(base: PathBuf) -> Self {
+        Recorder {
+            path: base.clone(),
+            base,
+            matches: 0,
+            replay: Replay::new(0, MatchSettings::default()),
+        }
+    }
+
+    /// Starts recording the next match, played on `seed` with `settings`.
+    pub fn start(&mut self, seed: u64, settings: MatchSettings) {
+        self.matches += 1;
+        self.path = if self.matches == 1 {
+            self.base.clone()
+        } else {
+            let stem = self.base.file_stem().unwrap_or_default().to_string_lossy();
+            let mut name = format!("{}-{}", stem, self.matches);
+            if let Some(extension) = self.base.extension() {
+                name += &format!(".{}", extension.to_string_lossy());
+            }
+            self.base.with_file_name(name)
+        };
+        self.replay = Replay::new(seed, settings);
+    }
+}
+
+/// The replay being played back in place of player input and enemy AI.
+pub struct Playback {
+    replay: Replay,
+    next: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Playback { replay, next: 0 }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.replay.records.len()
+    }
+
+    /// The settings to set the match up with again, map seed included.
+    pub fn settings(&self) -> MatchSettings {
+        MatchSettings {
+            seed: Some(self.replay.seed),
+            ..self.replay.settings.clone()
+        }
+    }
+
+    /// Starts over from the first record, to watch the match again.
+    pub fn rewind(&mut self) {
+        self.next = 0;
+    }
+}
+
+/// Writes the recording out whenever something new was recorded, so it survives the window
+/// being closed mid-match.
+pub fn save_recording(recorder: Res<Recorder>) {
+    if recorder.is_changed() {
+        if let Err(err) = recorder.replay.save(&recorder.path) {
+            error!("could not save replay to {:?}: {}", recorder.path, err);
+        }
+    }
+}
+
+/// Applies the next recorded rule call.
+pub fn play_back(
+    mut playback: ResMut<Playback>,
+    mut orders: Orders,
+    app_state: Res<State<AppState>>,
+) {
+    if playback.is_finished() || app_state.current() != &AppState::InGame {
+        return;
+    }
+
+    match playback.replay.records[playback.next] {
+        Record::Action { action, .. } => orders.issue(action),
+        Record::Damage {
+            target,
+            amount,
+            kind,
+            source,
+            ..
+        } => orders.damage(target, amount, kind, source),
+        Record::Miss { .. } => orders.miss(),
+    }
+    playback.next += 1;
+}
@@ -0,0 +1,1455 @@
+//! Turn, movement and combat rules, independent of rendering and input.
+//!
+//! [`GameState`] owns every ship and rock in the battle, the [`Phase`] of the round and the
+//! [`Round`] counter. Bevy
+//! systems translate keyboard input, AI decisions and cannonball collisions into
+//! [`apply_action`] / [`apply_damage`] / [`apply_miss`] calls and mirror the state back onto the
+//! ECS components afterwards.
+
+use bevy::math::{Quat, Vec2, Vec3};
+use bevy::prelude::Component;
+use serde::{Deserialize, Serialize};
+
+/// Sails torn by a chain shot hit.
+const CHAIN_SHOT_SAILS: i32 = 2;
+/// Turns a chain shot hit fouls the target's rigging for.
+const CHAIN_SHOT_TURNS: u32 = 1;
+/// Crew killed by a grape shot hit.
+const GRAPE_SHOT_CREW: i32 = 2;
+/// Turns a heated shot hit keeps the target burning.
+const HEATED_SHOT_TURNS: u32 = 2;
+/// Distance a rammer covers in a move for each point of damage it deals a ship struck
+/// broadside on. Glancing blows along the target's keel deal half as much.
+const RAMMING_DISTANCE_PER_DAMAGE: f32 = 10.0;
+/// Size of a wreck relative to the ship that went down.
+const WRECK_SIZE: f32 = 0.75;
+/// Turns an enemy ship that ran onto a rock sits out.
+const STUNNED_TURNS: u32 = 1;
+/// Turns a ship whose sails were shot away drifts before rigging a jury mast.
+const DISMASTED_TURNS: u32 = 2;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ShipId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Side {
+    Player,
+    Enemy,
+}
+
+impl Side {
+    pub fn opponent(self) -> Side {
+        match self {
+            Side::Player => Side::Enemy,
+            Side::Enemy => Side::Player,
+        }
+    }
+}
+
+/// Steps of a round. Ships act during the input phases; each resolve phase lasts until
+/// every cannonball fired has landed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Phase {
+    PlayerInput,
+    PlayerResolve,
+    EnemyPlan,
+    EnemyResolve,
+    EndOfRound,
+    /// The match is over, see [`GameState::outcome`]; nothing can act any more.
+    Finished,
+}
+
+impl Phase {
+    /// The side whose ships may act in this phase.
+    pub fn acting_side(self) -> Option<Side> {
+        match self {
+            Phase::PlayerInput => Some(Side::Player),
+            Phase::EnemyPlan => Some(Side::Enemy),
+            _ => None,
+        }
+    }
+
+    fn next(self) -> Phase {
+        match self {
+            Phase::PlayerInput => Phase::PlayerResolve,
+            Phase::PlayerResolve => Phase::EnemyPlan,
+            Phase::EnemyPlan => Phase::EnemyResolve,
+            Phase::EnemyResolve => Phase::EndOfRound,
+            Phase::EndOfRound => Phase::PlayerInput,
+            Phase::Finished => Phase::Finished,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Steer {
+    Forward,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Aim {
+    /// Every gun on both sides of the hull.
+    Broadside,
+    /// The guns of one side, trained along the given `Direction`. Only the two directions
+    /// abeam of the ship can be fired on.
+    Heading(i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    Steer {
+        ship: ShipId,
+        steer: Steer,
+    },
+    Fire {
+        ship: ShipId,
+        aim: Aim,
+    },
+    /// Load the guns with `ammo` for the next volley, without using up the turn.
+    Load {
+        ship: ShipId,
+        ammo: Ammo,
+    },
+    /// Strike the colours, handing the match to the other side.
+    Surrender(Side),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Obstacle {
+    Rock(usize),
+    Ship(ShipId),
+    Wreck(usize),
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Ammo {
+    /// Plain shot holing the hull.
+    Round,
+    /// Tears the sails and fouls the rigging without harming the hull.
+    Chain,
+    /// Kills crew without harming the hull.
+    Grape,
+    /// Sets the target burning for a few turns.
+    Heated,
+}
+
+impl Ammo {
+    pub const ALL: [Ammo; 4] = [Ammo::Round, Ammo::Chain, Ammo::Grape, Ammo::Heated];
+
+    /// Hull damage dealt by a ball.
+    pub fn damage(self) -> i32 {
+        match self {
+            Ammo::Round | Ammo::Heated => 1,
+            Ammo::Chain | Ammo::Grape => 0,
+        }
+    }
+}
+
+/// Volleys of each special ammo a ship carries. Round shot never runs out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AmmoStocks {
+    pub chain: u32,
+    pub grape: u32,
+    pub heated: u32,
+}
+
+impl AmmoStocks {
+    /// Volleys of `ammo` left, `None` if it never runs out.
+    pub fn get(&self, ammo: Ammo) -> Option<u32> {
+        match ammo {
+            Ammo::Round => None,
+            Ammo::Chain => Some(self.chain),
+            Ammo::Grape => Some(self.grape),
+            Ammo::Heated => Some(self.heated),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == AmmoStocks::default()
+    }
+
+    fn take(&mut self, ammo: Ammo) {
+        match ammo {
+            Ammo::Round => (),
+            Ammo::Chain => self.chain = self.chain.saturating_sub(1),
+            Ammo::Grape => self.grape = self.grape.saturating_sub(1),
+            Ammo::Heated => self.heated = self.heated.saturating_sub(1),
+        }
+    }
+
+    fn add(&mut self, other: AmmoStocks) {
+        self.chain += other.chain;
+        self.grape += other.grape;
+        self.heated += other.heated;
+    }
+}
+
+/// Where a cannonball struck a ship, which decides what besides the hull it damages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Quarter {
+    /// Raking the bow brings down sails.
+    Bow,
+    Beam,
+    /// Raking the stern wrecks the rudder.
+    Stern,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DamageKind {
+    Cannonball(Ammo, Quarter),
+    Rock,
+    Ramming,
+    Fire,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    Moved {
+        ship: ShipId,
+        from: Vec2,
+        to: Vec2,
+    },
+    /// One cannonball of `ammo` per shot leaves the ship's guns.
+    Fired {
+        ship: ShipId,
+        ammo: Ammo,
+        shots: Vec<Shot>,
+    },
+    /// The ship's guns are loaded with `ammo` for its next volley.
+    Loaded {
+        ship: ShipId,
+        ammo: Ammo,
+    },
+    /// The ship's move was cut short where it ran into `obstacle`.
+    Collided {
+        ship: ShipId,
+        obstacle: Obstacle,
+    },
+    Afflicted {
+        ship: ShipId,
+        effect: StatusEffect,
+        turns: u32,
+    },
+    /// The ship took damage of any kind. This is the one event to watch for every hit.
+    Damaged {
+        ship: ShipId,
+        amount: i32,
+        health: i32,
+        kind: DamageKind,
+    },
+    /// The ship went down, leaving the last of [`GameState::wrecks`].
+    Sunk {
+        ship: ShipId,
+    },
+    /// The ship ran into a wreck and took aboard the ammo it went down with.
+    Salvaged {
+        ship: ShipId,
+        wreck: usize,
+        ammo: AmmoStocks,
+    },
+    PhaseStarted(Phase),
+    GameOver(Outcome),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameOverReason {
+    /// A fleet went down to cannon fire or a collision between ships.
+    Sunk,
+    /// A fleet was wrecked on the rocks.
+    RanAground,
+    /// The last round was played with both fleets afloat.
+    RoundsExpired,
+    Surrendered,
+}
+
+/// How the match ended. `winner` is `None` for a draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Outcome {
+    pub winner: Option<Side>,
+    pub reason: GameOverReason,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FleetStats {
+    pub shots_fired: u32,
+    pub hits: u32,
+}
+
+/// Gunnery tallied over the whole match, per side.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BattleStats {
+    pub player: FleetStats,
+    pub enemy: FleetStats,
+}
+
+impl BattleStats {
+    pub fn side(&self, side: Side) -> &FleetStats {
+        match side {
+            Side::Player => &self.player,
+            Side::Enemy => &self.enemy,
+        }
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut FleetStats {
+        match side {
+            Side::Player => &mut self.player,
+            Side::Enemy => &mut self.enemy,
+        }
+    }
+}
+
+/// A cannonball leaving a gun's muzzle at `origin`, flying along the unit vector `direction`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shot {
+    pub origin: Vec2,
+    pub direction: Vec2,
+}
+
+/// How a ship's guns are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Guns {
+    /// How far the cannonballs fly before dropping into the sea, from the ship's centre.
+    pub range: f32,
+    /// Guns on each side of the hull, all fired in a broadside.
+    pub per_side: u32,
+    /// Angle in degrees the balls of one side fan out over.
+    pub spread_degrees: f32,
+    /// Distance from the ship's centre to the muzzles, which should clear the hull.
+    pub muzzle_offset: f32,
+}
+
+/// How every ship of a class sails and fights.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Hull {
+    /// Hull integrity a new ship starts with.
+    pub health: i32,
+    /// Sails condition when undamaged. Torn sails slow the ship down.
+    pub sails: i32,
+    /// Rudder condition when undamaged. A damaged rudder turns the ship less.
+    pub rudder: i32,
+    /// Distance covered by each half-step of a move.
+    pub speed: f32,
+    /// A move is made of two half-steps, each turning the ship by this angle in degrees.
+    pub turn_rate: f32,
+    pub radius: f32,
+    /// Action points the ship can bank.
+    pub max_action_points: i32,
+    /// Action points spent by one volley.
+    pub fire_cost: i32,
+    pub guns: Guns,
+    /// Sailors manning the guns. Reloading takes an extra action point once half of them
+    /// are lost, and the guns fall silent once they all are.
+    pub crew: i32,
+    /// Special ammo carried into battle.
+    pub ammo: AmmoStocks,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ship {
+    pub side: Side,
+    /// Name of the class the ship was built from.
+    pub class: String,
+    pub hull: Hull,
+    pub position: Vec2,
+    /// Rotation around the z axis in radians, 0 facing up.
+    pub rotation: f32,
+    /// See [`crate::Direction`], the nearest to `rotation`.
+    pub direction: i32,
+    /// Hull integrity; the ship sinks at 0.
+    pub health: i32,
+    pub sails: i32,
+    pub rudder: i32,
+    pub crew: i32,
+    pub action_points: i32,
+    /// Ammo for the next volley.
+    pub loaded: Ammo,
+    /// Special ammo left.
+    pub ammo: AmmoStocks,
+    pub effects: Vec<Status>,
+    /// Whether the ship has already moved this turn.
+    pub moved: bool,
+}
+
+impl Ship {
+    pub fn new(side: Side, class: &str, hull: Hull, position: Vec2, direction: i32) -> Self {
+        Ship {
+            side,
+            class: class.to_string(),
+            hull,
+            position,
+            rotation: direction_rotation(direction),
+            direction,
+            health: hull.health,
+            sails: hull.sails,
+            rudder: hull.rudder,
+            crew: hull.crew,
+            action_points: 0,
+            loaded: Ammo::Round,
+            ammo: hull.ammo,
+            effects: Vec::new(),
+            moved: false,
+        }
+    }
+
+    pub fn is_afloat(&self) -> bool {
+        self.health > 0
+    }
+
+    pub fn has(&self, effect: StatusEffect) -> bool {
+        self.effects.iter().any(|status| status.effect == effect)
+    }
+
+    /// Distance covered by each half-step of a move, down to a quarter of the hull's speed
+    /// with the sails in shreds.
+    pub fn speed(&self) -> f32 {
+        if self.has(StatusEffect::Dismasted) {
+            return 0.0;
+        }
+        let sails = self.sails as f32 / self.hull.sails.max(1) as f32;
+        let speed = self.hull.speed * (0.25 + 0.75 * sails);
+        if self.has(StatusEffect::Slowed) {
+            speed / 2.0
+        } else {
+            speed
+        }
+    }
+
+    /// Angle in degrees turned by each half-step of a turn.
+    pub fn turn_rate(&self) -> f32 {
+        self.hull.turn_rate * self.rudder as f32 / self.hull.rudder.max(1) as f32
+    }
+
+    /// Action points spent by one volley.
+    pub fn fire_cost(&self) -> i32 {
+        if self.crew * 2 < self.hull.crew {
+            self.hull.fire_cost + 1
+        } else {
+            self.hull.fire_cost
+        }
+    }
+
+    /// Action points the ship can bank, always enough for a volley.
+    pub fn max_action_points(&self) -> i32 {
+        self.hull.max_action_points.max(self.fire_cost())
+    }
+
+    /// The quarter a cannonball flying along `travel` strikes.
+    pub fn quarter(&self, travel: Vec2) -> Quarter {
+        let along = heading(self.rotation).dot(travel.normalize_or_zero());
+        if along > std::f32::consts::FRAC_1_SQRT_2 {
+            // flying the way the ship sails, so it came from astern
+            Quarter::Stern
+        } else if along < -std::f32::consts::FRAC_1_SQRT_2 {
+            Quarter::Bow
+        } else {
+            Quarter::Beam
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StatusEffect {
+    /// Sits its turns out.
+    Stunned,
+    /// Loses 1 health at the start of each of its turns.
+    Burning,
+    /// Moves at half speed.
+    Slowed,
+    /// Can only turn on the spot. Once it wears off the crew rigs a jury mast, giving the
+    /// ship a point of sails back.
+    Dismasted,
+}
+
+/// A status effect and how many more of the ship's turns it lasts. It is in force during
+/// the turn its count runs down to 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    pub effect: StatusEffect,
+    pub turns: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rock {
+    pub position: Vec2,
+    pub radius: f32,
+    /// Sprite rotation in radians; rocks collide as circles.
+    pub rotation: f32,
+    /// Which of the rock sprites to draw.
+    pub variant: usize,
+}
+
+/// What is left of a sunk ship: a hazard to navigation, holding the special ammo the ship
+/// went down with until someone salvages it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Wreck {
+    pub ship: ShipId,
+    pub position: Vec2,
+    pub radius: f32,
+    pub rotation: f32,
+    pub salvage: AmmoStocks,
+}
+
+/// The round being played, counting from 1, and the round the match ends after.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Round {
+    pub current: u32,
+    pub max: u32,
+}
+
+/// How a ship's course lies to the wind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointOfSail {
+    /// The wind right astern.
+    Running,
+    /// The wind abeam or on the quarter, the fastest point of sail.
+    Reaching,
+    /// Sailing close to the wind.
+    Beating,
+    /// Heading straight into the wind, barely making way.
+    InIrons,
+}
+
+/// The `Direction` the wind blows towards and its strength, from 0 for a calm to 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Wind {
+    pub direction: i32,
+    pub strength: f32,
+}
+
+impl Wind {
+    pub fn point_of_sail(&self, direction: i32) -> PointOfSail {
+        match (direction - self.direction).rem_euclid(8) {
+            0 => PointOfSail::Running,
+            3 | 5 => PointOfSail::Beating,
+            4 => PointOfSail::InIrons,
+            _ => PointOfSail::Reaching,
+        }
+    }
+
+    /// How much the wind stretches or shortens a move along `direction`.
+    pub fn speed_factor(&self, direction: i32) -> f32 {
+        let effect = match self.point_of_sail(direction) {
+            PointOfSail::Running => 0.25,
+            PointOfSail::Reaching => 0.5,
+            PointOfSail::Beating => -0.4,
+            PointOfSail::InIrons => -0.8,
+        };
+        1.0 + effect * self.strength
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameState {
+    pub ships: Vec<Ship>,
+    pub rocks: Vec<Rock>,
+    pub wrecks: Vec<Wreck>,
+    pub phase: Phase,
+    pub round: Round,
+    /// Set once the match is decided.
+    pub outcome: Option<Outcome>,
+    pub stats: BattleStats,
+    /// The wind of every round, forecast when the match is set up.
+    pub winds: Vec<Wind>,
+    /// Whether cannonballs can damage ships of the side that fired them.
+    pub friendly_fire: bool,
+    /// Turns played so far; each side's turn counts separately.
+    pub turn_number: u32,
+    /// Cannonballs fired that have not hit or missed yet.
+    pub shots_in_flight: u32,
+    /// Collisions already paid for this turn, each ship pair listed once.
+    pub collisions: Vec<(ShipId, Obstacle)>,
+    pub bounds: Vec2,
+}
+
+impl GameState {
+    pub fn new(bounds: Vec2, max_rounds: u32) -> Self {
+        GameState {
+            ships: Vec::new(),
+            rocks: Vec::new(),
+            wrecks: Vec::new(),
+            phase: Phase::PlayerInput,
+            round: Round {
+                current: 1,
+                max: max_rounds,
+            },
+            outcome: None,
+            stats: BattleStats::default(),
+            winds: Vec::new(),
+            friendly_fire: false,
+            turn_number: 0,
+            shots_in_flight: 0,
+            collisions: Vec::new(),
+            bounds,
+        }
+    }
+
+    /// The wind this round, calm if none was forecast.
+    pub fn wind(&self) -> Wind {
+        let round = self.round.current.saturating_sub(1) as usize;
+        self.winds.get(round).copied().unwrap_or_default()
+    }
+
+    pub fn add_ship(&mut self, ship: Ship) -> ShipId {
+        self.ships.push(ship);
+        ShipId(self.ships.len() - 1)
+    }
+
+    pub fn add_rock(&mut self, rock: Rock) {
+        self.rocks.push(rock);
+    }
+
+    pub fn ship(&self, id: ShipId) -> &Ship {
+        &self.ships[id.0]
+    }
+
+    /// Ships of `side` that are still afloat.
+    pub fn fleet(&self, side: Side) -> impl Iterator<Item = (ShipId, &Ship)> {
+        self.ships
+            .iter()
+            .enumerate()
+            .filter(move |(_, ship)| ship.side == side && ship.is_afloat())
+            .map(|(i, ship)| (ShipId(i), ship))
+    }
+
+    /// The side left afloat once the other one has sunk.
+    pub fn winner(&self) -> Option<Side> {
+        let afloat = |side| self.fleet(side).next().is_some();
+        match (afloat(Side::Player), afloat(Side::Enemy)) {
+            (true, false) => Some(Side::Player),
+            (false, true) => Some(Side::Enemy),
+            _ => None,
+        }
+    }
+
+    /// The side with more health left when the rounds run out. `None` is a draw.
+    fn leader(&self) -> Option<Side> {
+        match self
+            .fleet_health(Side::Player)
+            .cmp(&self.fleet_health(Side::Enemy))
+        {
+            std::cmp::Ordering::Greater => Some(Side::Player),
+            std::cmp::Ordering::Less => Some(Side::Enemy),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    /// Total health left in the fleet of `side`.
+    pub fn fleet_health(&self, side: Side) -> i32 {
+        self.fleet(side).map(|(_, ship)| ship.health).sum()
+    }
+
+    pub fn can_act(&self, id: ShipId) -> bool {
+        self.ships.get(id.0).is_some_and(|ship| {
+            self.phase.acting_side() == Some(ship.side) && ship.is_afloat() && !ship.moved
+        })
+    }
+
+    fn steer(&mut self, id: ShipId, steer: Steer, events: &mut Vec<GameEvent>) {
+        if !self.can_act(id) {
+            return;
+        }
+
+        let rotation_factor = match steer {
+            Steer::Forward => 0.0,
+            Steer::Left => 1.0,
+            Steer::Right => -1.0,
+        };
+        let extents = self.bounds / 2.0;
+        let wind = self.wind();
+        let ship = &self.ships[id.0];
+        let from = ship.position;
+
+        // A ship that runs into something stops where it touches, but still answers the helm.
+        let (speed, turn_rate) = (ship.speed(), ship.turn_rate());
+        let (mut position, mut rotation) = (ship.position, ship.rotation);
+        let mut blocked = None;
+        for _ in 0..2 {
+            if blocked.is_none() {
+                let wind_factor = wind.speed_factor(rotation_direction(rotation));
+                let to = (position + heading(rotation) * speed * wind_factor)
+                    .min(extents)
+                    .max(-extents);
+                match self.first_contact(id, position, to) {
+                    Some((obstacle, t)) => {
+                        position = position.lerp(to, t);
+                        blocked = Some(obstacle);
+                    }
+                    None => position = to,
+                }
+            }
+            rotation += rotation_factor * f32::to_radians(turn_rate);
+        }
+
+        let ship = &mut self.ships[id.0];
+        ship.position = position;
+        ship.rotation = rotation;
+        ship.direction = rotation_direction(rotation);
+        ship.action_points = (ship.action_points + 1).min(ship.max_action_points());
+        ship.moved = true;
+
+        events.push(GameEvent::Moved {
+            ship: id,
+            from,
+            to: position,
+        });
+        if let Some(obstacle) = blocked {
+            self.collide(id, obstacle, position - from, events);
+        }
+        self.end_input_if_done(events);
+    }
+
+    fn fire(&mut self, id: ShipId, aim: Aim, events: &mut Vec<GameEvent>) {
+        if !self.can_act(id) {
+            return;
+        }
+        let ship = &mut self.ships[id.0];
+        if ship.action_points < ship.fire_cost() || ship.crew <= 0 {
+            return;
+        }
+        if let Aim::Heading(d) = aim {
+            // the guns don't bear over the bow or stern
+            if (d - ship.direction).rem_euclid(4) != 2 {
+                return;
+            }
+        }
+
+        let guns = ship.hull.guns;
+        let directions = match aim {
+            Aim::Broadside => {
+                let right_angle = std::f32::consts::FRAC_PI_2;
+                let mut directions = fan(ship.rotation + right_angle, &guns);
+                directions.extend(fan(ship.rotation - right_angle, &guns));
+                directions
+            }
+            Aim::Heading(d) => fan(direction_rotation(d), &guns),
+        };
+        ship.action_points -= ship.fire_cost();
+        let ammo = ship.loaded;
+        ship.ammo.take(ammo);
+        if ship.ammo.get(ammo) == Some(0) {
+            ship.loaded = Ammo::Round;
+        }
+        let (side, position) = (ship.side, ship.position);
+        self.shots_in_flight += directions.len() as u32;
+        self.stats.side_mut(side).shots_fired += directions.len() as u32;
+
+        events.push(GameEvent::Fired {
+            ship: id,
+            ammo,
+            shots: directions
+                .into_iter()
+                .map(|direction| Shot {
+                    origin: position + direction * guns.muzzle_offset,
+                    direction,
+                })
+                .collect(),
+        });
+    }
+
+    fn load(&mut self, id: ShipId, ammo: Ammo, events: &mut Vec<GameEvent>) {
+        if !self.can_act(id) {
+            return;
+        }
+        let ship = &mut self.ships[id.0];
+        if ship.ammo.get(ammo) != Some(0) {
+            ship.loaded = ammo;
+            events.push(GameEvent::Loaded { ship: id, ammo });
+        }
+    }
+
+    /// The first obstacle a ship moving from `from` to `to` runs into, and how far along the
+    /// way it touches it, from 0 to 1.
+    fn first_contact(&self, id: ShipId, from: Vec2, to: Vec2) -> Option<(Obstacle, f32)> {
+        let radius = self.ships[id.0].hull.radius;
+        let rocks = self
+            .rocks
+            .iter()
+            .enumerate()
+            .map(|(i, rock)| (Obstacle::Rock(i), rock.position, rock.radius));
+        let ships = self
+            .ships
+            .iter()
+            .enumerate()
+            .filter(|&(i, ship)| i != id.0 && ship.is_afloat())
+            .map(|(i, ship)| (Obstacle::Ship(ShipId(i)), ship.position, ship.hull.radius));
+        let wrecks = self
+            .wrecks
+            .iter()
+            .enumerate()
+            .map(|(i, wreck)| (Obstacle::Wreck(i), wreck.position, wreck.radius));
+        rocks
+            .chain(ships)
+            .chain(wrecks)
+            .filter_map(|(obstacle, centre, obstacle_radius)| {
+                contact_time(from, to, centre, radius + obstacle_radius).map(|t| (obstacle, t))
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+    }
+
+    /// Damages the ship that ran into `obstacle` after moving by `travel`, and the ship it
+    /// rammed. Ships of the same side only foul each other unless friendly fire is on. Wrecks
+    /// do no harm, but give up their ammo. Each pair only collides once a turn.
+    fn collide(
+        &mut self,
+        id: ShipId,
+        obstacle: Obstacle,
+        travel: Vec2,
+        events: &mut Vec<GameEvent>,
+    ) {
+        events.push(GameEvent::Collided { ship: id, obstacle });
+        let pair = match obstacle {
+            Obstacle::Ship(other) if other.0 < id.0 => (other, Obstacle::Ship(id)),
+            _ => (id, obstacle),
+        };
+        if self.collisions.contains(&pair) {
+            return;
+        }
+        self.collisions.push(pair);
+
+        match obstacle {
+            Obstacle::Rock(_) => {
+                match self.ships[id.0].side {
+                    Side::Player => {
+                        self.damage(id, 1, DamageKind::Rock, events);
+                    }
+                    Side::Enemy => self.afflict(id, StatusEffect::Stunned, STUNNED_TURNS, events),
+                }
+                self.end_if_fleet_lost(GameOverReason::RanAground, events);
+            }
+            // without friendly fire, ships of a fleet only block each other
+            Obstacle::Ship(other)
+                if self.ships[other.0].side == self.ships[id.0].side && !self.friendly_fire => {}
+            Obstacle::Ship(other) => {
+                let inflicted = ramming_damage(travel, self.ships[other.0].rotation);
+                self.damage(other, inflicted, DamageKind::Ramming, events);
+                // the bow takes the blow better than the side it strikes
+                if inflicted / 2 > 0 {
+                    self.damage(id, inflicted / 2, DamageKind::Ramming, events);
+                }
+                self.end_if_fleet_lost(GameOverReason::Sunk, events);
+            }
+            Obstacle::Wreck(wreck) => {
+                let ammo = std::mem::take(&mut self.wrecks[wreck].salvage);
+                if !ammo.is_empty() {
+                    self.ships[id.0].ammo.add(ammo);
+                    events.push(GameEvent::Salvaged {
+                        ship: id,
+                        wreck,
+                        ammo,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Returns whether the ship took the damage.
+    fn damage(
+        &mut self,
+        id: ShipId,
+        amount: i32,
+        kind: DamageKind,
+        events: &mut Vec<GameEvent>,
+    ) -> bool {
+        let ship = &mut self.ships[id.0];
+        if !ship.is_afloat() || self.outcome.is_some() {
+            return false;
+        }
+
+        ship.health -= amount;
+        let had_sails = ship.sails > 0;
+        let mut effect = None;
+        if let DamageKind::Cannonball(ammo, quarter) = kind {
+            match ammo {
+                Ammo::Round => match quarter {
+                    Quarter::Bow => ship.sails -= 1,
+                    Quarter::Beam => (),
+                    Quarter::Stern => ship.rudder -= 1,
+                },
+                Ammo::Chain => {
+                    ship.sails -= CHAIN_SHOT_SAILS;
+                    effect = Some((StatusEffect::Slowed, CHAIN_SHOT_TURNS));
+                }
+                Ammo::Grape => ship.crew -= GRAPE_SHOT_CREW,
+                Ammo::Heated => effect = Some((StatusEffect::Burning, HEATED_SHOT_TURNS)),
+            }
+            ship.sails = ship.sails.max(0);
+            ship.rudder = ship.rudder.max(0);
+            ship.crew = ship.crew.max(0);
+        }
+        let dismasted = had_sails && ship.sails == 0;
+        events.push(GameEvent::Damaged {
+            ship: id,
+            amount,
+            health: ship.health,
+            kind,
+        });
+        if !ship.is_afloat() {
+            ship.effects.clear();
+            self.wrecks.push(Wreck {
+                ship: id,
+                position: ship.position,
+                radius: ship.hull.radius * WRECK_SIZE,
+                rotation: ship.rotation,
+                salvage: ship.ammo,
+            });
+            events.push(GameEvent::Sunk { ship: id });
+            return true;
+        }
+        if let Some((effect, turns)) = effect {
+            self.afflict(id, effect, turns, events);
+        }
+        if dismasted {
+            self.afflict(id, StatusEffect::Dismasted, DISMASTED_TURNS, events);
+        }
+        true
+    }
+
+    /// Puts `effect` on the ship for its next `turns` turns, unless it already lasts longer.
+    fn afflict(
+        &mut self,
+        id: ShipId,
+        effect: StatusEffect,
+        turns: u32,
+        events: &mut Vec<GameEvent>,
+    ) {
+        let ship = &mut self.ships[id.0];
+        match ship
+            .effects
+            .iter_mut()
+            .find(|status| status.effect == effect)
+        {
+            Some(status) => status.turns = status.turns.max(turns),
+            None => ship.effects.push(Status { effect, turns }),
+        }
+        events.push(GameEvent::Afflicted {
+            ship: id,
+            effect,
+            turns,
+        });
+    }
+
+    /// Ends the match once a whole fleet has gone down.
+    fn end_if_fleet_lost(&mut self, reason: GameOverReason, events: &mut Vec<GameEvent>) {
+        let lost = |side| self.fleet(side).next().is_none();
+        if self.outcome.is_none() && (lost(Side::Player) || lost(Side::Enemy)) {
+            let winner = self.winner();
+            self.finish(Outcome { winner, reason }, events);
+        }
+    }
+
+    fn surrender(&mut self, side: Side, events: &mut Vec<GameEvent>) {
+        if self.outcome.is_some() {
+            return;
+        }
+        let outcome = Outcome {
+            winner: Some(side.opponent()),
+            reason: GameOverReason::Surrendered,
+        };
+        self.finish(outcome, events);
+    }
+
+    fn finish(&mut self, outcome: Outcome, events: &mut Vec<GameEvent>) {
+        self.outcome = Some(outcome);
+        self.enter(Phase::Finished, events);
+        events.push(GameEvent::GameOver(outcome));
+    }
+
+    /// Moves on to the resolve phase once every ship of the acting side has moved.
+    fn end_input_if_done(&mut self, events: &mut Vec<GameEvent>) {
+        let side = match self.phase.acting_side() {
+            Some(side) => side,
+            None => return,
+        };
+        if self.fleet(side).any(|(_, ship)| !ship.moved) {
+            return;
+        }
+
+        for ship in self.ships.iter_mut() {
+            ship.moved = false;
+        }
+        self.end_turn(side);
+        self.turn_number += 1;
+        self.enter(self.phase.next(), events);
+        self.end_resolve_if_done(events);
+    }
+
+    /// Moves on from a resolve phase once every cannonball has landed.
+    fn end_resolve_if_done(&mut self, events: &mut Vec<GameEvent>) {
+        if !matches!(self.phase, Phase::PlayerResolve | Phase::EnemyResolve)
+            || self.shots_in_flight > 0
+        {
+            return;
+        }
+
+        self.enter(self.phase.next(), events);
+        if self.phase == Phase::EndOfRound {
+            if self.round.current >= self.round.max {
+                let outcome = Outcome {
+                    winner: self.leader(),
+                    reason: GameOverReason::RoundsExpired,
+                };
+                self.finish(outcome, events);
+                return;
+            }
+            self.round.current += 1;
+            self.enter(self.phase.next(), events);
+        }
+        self.start_turn(events);
+    }
+
+    /// Applies the status effects of the side about to act: burning ships lose 1 health and
+    /// stunned ones sit the turn out. Every effect then has a turn less to run.
+    fn start_turn(&mut self, events: &mut Vec<GameEvent>) {
+        let side = match self.phase.acting_side() {
+            Some(side) => side,
+            None => return,
+        };
+        for i in 0..self.ships.len() {
+            let ship = &self.ships[i];
+            if ship.side != side || !ship.is_afloat() {
+                continue;
+            }
+            if ship.has(StatusEffect::Burning) {
+                self.damage(ShipId(i), 1, DamageKind::Fire, events);
+            }
+            let ship = &mut self.ships[i];
+            ship.moved = ship.has(StatusEffect::Stunned);
+            for status in ship.effects.iter_mut() {
+                status.turns = status.turns.saturating_sub(1);
+            }
+        }
+        self.end_if_fleet_lost(GameOverReason::Sunk, events);
+        if self.outcome.is_none() {
+            self.end_input_if_done(events);
+        }
+    }
+
+    /// Clears the effects that ran out during the acting side's turn, and its collisions.
+    fn end_turn(&mut self, side: Side) {
+        self.collisions.clear();
+        for ship in self.ships.iter_mut().filter(|ship| ship.side == side) {
+            let jury_rigged = ship
+                .effects
+                .iter()
+                .any(|status| status.effect == StatusEffect::Dismasted && status.turns == 0);
+            if jury_rigged {
+                ship.sails = ship.sails.max(1);
+            }
+            ship.effects.retain(|status| status.turns > 0);
+        }
+    }
+
+    fn enter(&mut self, phase: Phase, events: &mut Vec<GameEvent>) {
+        self.phase = phase;
+        events.push(GameEvent::PhaseStarted(phase));
+    }
+
+    fn land_shot(&mut self, events: &mut Vec<GameEvent>) {
+        self.shots_in_flight = self.shots_in_flight.saturating_sub(1);
+        self.end_resolve_if_done(events);
+    }
+}
+
+/// Applies `action` if it is legal in `state`, returning what happened.
+///
+/// Illegal actions (out of turn, sunk ship, not enough action points) leave the state
+/// untouched and produce no events.
+pub fn apply_action(state: &mut GameState, action: Action) -> Vec<GameEvent> {
+    let mut events = Vec::new();
+    match action {
+        Action::Steer { ship, steer } => state.steer(ship, steer, &mut events),
+        Action::Fire { ship, aim } => state.fire(ship, aim, &mut events),
+        Action::Load { ship, ammo } => state.load(ship, ammo, &mut events),
+        Action::Surrender(side) => state.surrender(side, &mut events),
+    }
+    events
+}
+
+/// Applies damage dealt to `target` outside the rules, such as a cannonball hit found by the
+/// physics engine, by the ship `source` if there is one. A cannonball also counts as landed.
+///
+/// Damage between ships of the same side is ignored unless friendly fire is on.
+pub fn apply_damage(
+    state: &mut GameState,
+    target: ShipId,
+    amount: i32,
+    kind: DamageKind,
+    source: Option<ShipId>,
+) -> Vec<GameEvent> {
+    let mut events = Vec::new();
+    let side = state.ship(target).side;
+    let friendly = source.is_some_and(|source| state.ship(source).side == side);
+    if (!friendly || state.friendly_fire) && state.damage(target, amount, kind, &mut events) {
+        let reason = match kind {
+            DamageKind::Cannonball(..) => {
+                if !friendly {
+                    state.stats.side_mut(side.opponent()).hits += 1;
+                }
+                GameOverReason::Sunk
+            }
+            DamageKind::Rock => GameOverReason::RanAground,
+            DamageKind::Ramming | DamageKind::Fire => GameOverReason::Sunk,
+        };
+        state.end_if_fleet_lost(reason, &mut events);
+    }
+    if matches!(kind, DamageKind::Cannonball(..)) {
+        state.land_shot(&mut events);
+    }
+    events
+}
+
+/// Reports a cannonball that left the map without hitting anything.
+pub fn apply_miss(state: &mut GameState) -> Vec<GameEvent> {
+    let mut events = Vec::new();
+    state.land_shot(&mut events);
+    events
+}
+
+/// How far along the way from `from` to `to`, from 0 to 1, a ship first comes within `reach`
+/// of `centre`. Moving away from or along something it already touches is never blocked.
+fn contact_time(from: Vec2, to: Vec2, centre: Vec2, reach: f32) -> Option<f32> {
+    let travel = to - from;
+    let offset = from - centre;
+    let closing = offset.dot(travel);
+    if closing >= 0.0 {
+        return None;
+    }
+    let gap = offset.length_squared() - reach * reach;
+    if gap <= 0.0 {
+        return Some(0.0);
+    }
+    let discriminant = closing * closing - travel.length_squared() * gap;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = (-closing - discriminant.sqrt()) / travel.length_squared();
+    (t <= 1.0).then_some(t)
+}
+
+/// Damage dealt by a ship that moved by `travel` into one heading along `target_rotation`,
+/// at least 1.
+fn ramming_damage(travel: Vec2, target_rotation: f32) -> i32 {
+    let broadside = match travel.try_normalize() {
+        Some(direction) => direction.perp_dot(heading(target_rotation)).abs(),
+        None => 0.0,
+    };
+    let damage = travel.length() / RAMMING_DISTANCE_PER_DAMAGE * (0.5 + 0.5 * broadside);
+    (damage.round() as i32).max(1)
+}
+
+/// Unit vector a ship with `rotation` sails along.
+pub fn heading(rotation: f32) -> Vec2 {
+    (Quat::from_rotation_z(rotation) * Vec3::Y).truncate()
+}
+
+/// Rotation of a ship sailing along `direction`, see [`crate::Direction`].
+pub fn direction_rotation(direction: i32) -> f32 {
+    -f32::to_radians(45.0 * direction.rem_euclid(8) as f32)
+}
+
+/// The `Direction` nearest to `rotation`.
+fn rotation_direction(rotation: f32) -> i32 {
+    (-rotation.to_degrees() / 45.0).round().rem_euclid(8.0) as i32
+}
+
+/// Headings of one side's guns, fanned out evenly around `rotation`.
+fn fan(rotation: f32, guns: &Guns) -> Vec<Vec2> {
+    let spread = f32::to_radians(guns.spread_degrees);
+    (0..guns.per_side)
+        .map(|i| {
+            let offset = if guns.per_side > 1 {
+                spread * (i as f32 / (guns.per_side - 1) as f32 - 0.5)
+            } else {
+                0.0
+            };
+            heading(rotation + offset)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hull() -> Hull {
+        Hull {
+            health: 5,
+            sails: 4,
+            rudder: 2,
+            speed: 10.0,
+            turn_rate: 22.5,
+            radius: 15.0,
+            max_action_points: 3,
+            fire_cost: 3,
+            guns: Guns {
+                range: 500.0,
+                per_side: 1,
+                spread_degrees: 0.0,
+                muzzle_offset: 26.0,
+            },
+            crew: 4,
+            ammo: AmmoStocks::default(),
+        }
+    }
+
+    /// A player ship sailing up from the bottom left and an enemy sailing down from the top
+    /// right, well clear of each other.
+    fn battle(max_rounds: u32) -> (GameState, ShipId, ShipId) {
+        let mut state = GameState::new(Vec2::new(1000.0, 1000.0), max_rounds);
+        let player = state.add_ship(Ship::new(
+            Side::Player,
+            "Test",
+            hull(),
+            Vec2::new(-300.0, -300.0),
+            0,
+        ));
+        let enemy = state.add_ship(Ship::new(
+            Side::Enemy,
+            "Test",
+            hull(),
+            Vec2::new(300.0, 300.0),
+            4,
+        ));
+        (state, player, enemy)
+    }
+
+    fn steer(state: &mut GameState, ship: ShipId) -> Vec<GameEvent> {
+        apply_action(
+            state,
+            Action::Steer {
+                ship,
+                steer: Steer::Forward,
+            },
+        )
+    }
+
+    fn phases(events: &[GameEvent]) -> Vec<Phase> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::PhaseStarted(phase) => Some(*phase),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn phases_follow_each_other_through_a_round() {
+        let (mut state, player, enemy) = battle(10);
+        assert_eq!(state.phase, Phase::PlayerInput);
+
+        let events = steer(&mut state, player);
+        assert_eq!(phases(&events), [Phase::PlayerResolve, Phase::EnemyPlan]);
+
+        let events = steer(&mut state, enemy);
+        assert_eq!(
+            phases(&events),
+            [Phase::EnemyResolve, Phase::EndOfRound, Phase::PlayerInput]
+        );
+        assert_eq!(state.round.current, 2);
+        assert_eq!(state.turn_number, 2);
+    }
+
+    #[test]
+    fn ships_act_only_in_their_own_turn() {
+        let (mut state, player, enemy) = battle(10);
+        assert!(steer(&mut state, enemy).is_empty());
+        steer(&mut state, player);
+        assert!(steer(&mut state, player).is_empty());
+        assert_eq!(state.phase, Phase::EnemyPlan);
+    }
+
+    #[test]
+    fn resolve_phase_waits_for_every_cannonball() {
+        let (mut state, player, _) = battle(10);
+        state.ships[player.0].action_points = 3;
+        let fire = Action::Fire {
+            ship: player,
+            aim: Aim::Broadside,
+        };
+        assert!(!apply_action(&mut state, fire).is_empty());
+        steer(&mut state, player);
+        assert_eq!(state.phase, Phase::PlayerResolve);
+        assert_eq!(state.shots_in_flight, 2);
+
+        apply_miss(&mut state);
+        assert_eq!(state.phase, Phase::PlayerResolve);
+        apply_miss(&mut state);
+        assert_eq!(state.phase, Phase::EnemyPlan);
+    }
+
+    #[test]
+    fn guns_only_train_abeam() {
+        let (mut state, player, _) = battle(10);
+        state.ships[player.0].action_points = 3;
+        for over_bow_or_stern in [0, 4] {
+            let fire = Action::Fire {
+                ship: player,
+                aim: Aim::Heading(over_bow_or_stern),
+            };
+            assert!(apply_action(&mut state, fire).is_empty());
+        }
+        let fire = Action::Fire {
+            ship: player,
+            aim: Aim::Heading(6),
+        };
+        assert!(!apply_action(&mut state, fire).is_empty());
+        assert_eq!(state.shots_in_flight, 1);
+    }
+
+    #[test]
+    fn running_out_of_rounds_is_a_draw_when_even() {
+        let (mut state, player, enemy) = battle(1);
+        steer(&mut state, player);
+        let events = steer(&mut state, enemy);
+        let outcome = Outcome {
+            winner: None,
+            reason: GameOverReason::RoundsExpired,
+        };
+        assert!(events.contains(&GameEvent::GameOver(outcome)));
+        assert_eq!(state.phase, Phase::Finished);
+    }
+
+    #[test]
+    fn running_out_of_rounds_goes_to_the_least_damaged() {
+        let (mut state, player, enemy) = battle(1);
+        apply_damage(&mut state, enemy, 1, DamageKind::Rock, None);
+        steer(&mut state, player);
+        steer(&mut state, enemy);
+        assert_eq!(
+            state.outcome,
+            Some(Outcome {
+                winner: Some(Side::Player),
+                reason: GameOverReason::RoundsExpired,
+            })
+        );
+    }
+
+    #[test]
+    fn moves_stop_where_the_ship_touches_a_rock() {
+        let (mut state, player, _) = battle(10);
+        state.add_rock(Rock {
+            position: Vec2::new(-300.0, -270.0),
+            radius: 10.0,
+            rotation: 0.0,
+            variant: 0,
+        });
+        let events = steer(&mut state, player);
+
+        let ship = state.ship(player);
+        assert!((ship.position.y - -295.0).abs() < 1e-3);
+        assert!(events.contains(&GameEvent::Collided {
+            ship: player,
+            obstacle: Obstacle::Rock(0),
+        }));
+        assert_eq!(ship.health, 4);
+    }
+
+    #[test]
+    fn contact_time_ignores_obstacles_moved_away_from() {
+        let centre = Vec2::ZERO;
+        let t = contact_time(Vec2::new(0.0, -40.0), Vec2::new(0.0, 0.0), centre, 20.0);
+        assert_eq!(t, Some(0.5));
+        assert_eq!(
+            contact_time(Vec2::new(0.0, -20.0), Vec2::new(0.0, -40.0), centre, 20.0),
+            None
+        );
+        assert_eq!(
+            contact_time(Vec2::new(30.0, -40.0), Vec2::new(30.0, 40.0), centre, 20.0),
+            None
+        );
+    }
+
+    #[test]
+    fn ramming_hits_harder_broadside_on() {
+        let travel = Vec2::new(0.0, 40.0);
+        assert_eq!(ramming_damage(travel, direction_rotation(2)), 4);
+        assert_eq!(ramming_damage(travel, direction_rotation(4)), 2);
+        assert_eq!(ramming_damage(Vec2::ZERO, 0.0), 1);
+    }
+
+    #[test]
+    fn a_pair_of_ships_only_collides_once_a_turn() {
+        let (mut state, player, _) = battle(10);
+        state.friendly_fire = true;
+        let consort = state.add_ship(Ship::new(
+            Side::Player,
+            "Test",
+            hull(),
+            Vec2::new(-300.0, -260.0),
+            4,
+        ));
+
+        steer(&mut state, player);
+        assert_eq!(state.ship(consort).health, 4);
+        assert_eq!(state.ship(player).health, 5);
+
+        // Backing into the ship that just rammed it does no further damage.
+        let events = steer(&mut state, consort);
+        assert!(events.contains(&GameEvent::Collided {
+            ship: consort,
+            obstacle: Obstacle::Ship(player),
+        }));
+        assert_eq!(state.ship(consort).health, 4);
+        assert_eq!(state.ship(player).health, 5);
+    }
+
+    #[test]
+    fn ships_of_a_fleet_only_block_each_other_without_friendly_fire() {
+        let (mut state, player, _) = battle(10);
+        let consort = state.add_ship(Ship::new(
+            Side::Player,
+            "Test",
+            hull(),
+            Vec2::new(-300.0, -260.0),
+            4,
+        ));
+        steer(&mut state, player);
+        assert_eq!(state.ship(consort).health, 5);
+        assert!((state.ship(player).position.y - -290.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn stunned_ships_sit_out_their_next_turn() {
+        let (mut state, player, enemy) = battle(10);
+        state.add_rock(Rock {
+            position: Vec2::new(300.0, 270.0),
+            radius: 10.0,
+            rotation: 0.0,
+            variant: 0,
+        });
+        steer(&mut state, player);
+        steer(&mut state, enemy);
+        assert!(state.ship(enemy).has(StatusEffect::Stunned));
+        let stranded = state.ship(enemy).position;
+
+        // The enemy's turn is skipped straight after the player's.
+        steer(&mut state, player);
+        assert_eq!(state.phase, Phase::PlayerInput);
+        assert_eq!(state.round.current, 3);
+        assert_eq!(state.ship(enemy).position, stranded);
+        assert!(!state.ship(enemy).has(StatusEffect::Stunned));
+    }
+
+    #[test]
+    fn burning_ships_lose_health_for_two_turns() {
+        let (mut state, player, enemy) = battle(10);
+        let heated = DamageKind::Cannonball(Ammo::Heated, Quarter::Beam);
+        apply_damage(&mut state, enemy, 1, heated, Some(player));
+        assert_eq!(state.ship(enemy).health, 4);
+        assert!(state.ship(enemy).has(StatusEffect::Burning));
+
+        for health in [3, 2, 2] {
+            steer(&mut state, player);
+            assert_eq!(state.ship(enemy).health, health);
+            steer(&mut state, enemy);
+        }
+        assert!(!state.ship(enemy).has(StatusEffect::Burning));
+    }
+}
@@ -0,0 +1,154 @@
+//! Saving the battle in progress and restoring it with `--load FILE`.
+//!
+//! The battle is written to [`SAVE_FILE`] at the start of every turn and whenever F5 is
+//! pressed, so closing the window loses at most the current turn.
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use heron::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::classes::ShipClasses;
+use crate::rules::{Ammo, GameState, ShipId};
+use crate::{Owner, Range, Textures};
+
+/// Bumped whenever the file format changes.
+pub const SAVE_VERSION: u32 = 1;
+
+pub const SAVE_FILE: &str = "battle.ron";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedCannonBall {
+    /// The ship that fired it.
+    pub owner: ShipId,
+    pub ammo: Ammo,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub range: Range,
+}
+
+/// The battle as it stood when the latest turn started, taken by `Orders` and written by
+/// [`save_battle`].
+#[derive(Default)]
+pub struct Autosave(pub Option<GameState>);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedBattle {
+    pub version: u32,
+    pub state: GameState,
+    pub cannonballs: Vec<SavedCannonBall>,
+}
+
+impl SavedBattle {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let saved: SavedBattle = ron::from_str(&fs::read_to_string(path)?)?;
+        if saved.version != SAVE_VERSION {
+            return Err(format!("unsupported save version {}", saved.version).into());
+        }
+        Ok(saved)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+/// Writes the snapshot taken when the latest turn started, or the battle as it stands when
+/// F5 is pressed.
+pub fn save_battle(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut autosave: ResMut<Autosave>,
+    state: Res<GameState>,
+    cannonballs: Query<(&Transform, &Velocity, &Owner, &Ammo, &Range)>,
+    ships: Query<&ShipId>,
+) {
+    let saved = if let Some(state) = autosave.0.take() {
+        // a turn only starts once every cannonball has landed
+        SavedBattle {
+            version: SAVE_VERSION,
+            state,
+            cannonballs: Vec::new(),
+        }
+    } else if keyboard_input.just_pressed(KeyCode::F5) {
+        SavedBattle {
+            version: SAVE_VERSION,
+            state: state.clone(),
+            cannonballs: cannonballs
+                .iter()
+                .filter_map(|(transform, velocity, &Owner(owner), &ammo, &range)| {
+                    Some(SavedCannonBall {
+                        owner: *ships.get(owner).ok()?,
+                        ammo,
+                        position: transform.translation.truncate(),
+                        velocity: velocity.linear.truncate(),
+                        range,
+                    })
+                })
+                .collect(),
+        }
+    } else {
+        return;
+    };
+    if saved.state.is_over() {
+        return;
+    }
+    // Balls fired this frame are not spawned yet. A battle saved without them would wait
+    // forever for them to land.
+    if saved.cannonballs.len() != saved.state.shots_in_flight as usize {
+        warn!(
+            "not saving the battle: {} cannonballs in flight, {} found",
+            saved.state.shots_in_flight,
+            saved.cannonballs.len()
+        );
+        return;
+    }
+    if let Err(err) = saved.save(SAVE_FILE) {
+        error!("could not save battle to {}: {}", SAVE_FILE, err);
+    }
+}
+
+/// Spawns the entities of a loaded battle, whose `GameState` is already in place.
+pub fn restore_battle(
+    commands: &mut Commands,
+    saved: &SavedBattle,
+    state: &GameState,
+    textures: &Textures,
+    classes: &ShipClasses,
+) {
+    for rock in state.rocks.iter() {
+        crate::spawn_rock(commands, textures, rock);
+    }
+    let ships: Vec<Entity> = state
+        .ships
+        .iter()
+        .enumerate()
+        .map(|(i, ship)| {
+            let class = classes
+                .get(&ship.class)
+                .expect("ship classes are checked at startup");
+            match state.wrecks.iter().find(|wreck| wreck.ship == ShipId(i)) {
+                // balls still flying from a sunk ship belong to its wreck
+                Some(wreck) => crate::spawn_wreck(commands, textures, class, ship.side, wreck),
+                None => crate::spawn_ship(commands, textures, class, ShipId(i), ship),
+            }
+        })
+        .collect();
+    for ball in saved.cannonballs.iter() {
+        crate::spawn_cannonball(
+            commands,
+            textures,
+            ships[ball.owner.0],
+            ball.ammo,
+            state.ship(ball.owner).side,
+            state.friendly_fire,
+            ball.position,
+            ball.velocity,
+            ball.range,
+        );
+    }
+}
@@ -1,109 +1,238 @@
+use bevy::app::AppExit;
 use bevy::prelude::*;
 
+use crate::rules::{
+    Ammo, FleetStats, GameEvent, GameOverReason, GameState, Obstacle, Outcome, PointOfSail, Ship,
+    ShipId, Side, StatusEffect,
+};
+
 #[derive(Component)]
 pub struct PlayerTextHealth;
 
+/// One line per enemy ship.
 #[derive(Component)]
-pub struct EnemyTextHealth;
+pub struct EnemyFleetText;
 
 #[derive(Component)]
 pub struct PlayerTextAmmo;
 
+/// The ammo loaded and the stocks left, next to [`PlayerTextAmmo`].
+#[derive(Component)]
+pub struct PlayerTextShot;
+
+#[derive(Component)]
+pub struct RoundText;
+
+#[derive(Component)]
+pub struct WindText;
+
+/// The player's status effects, next to [`PlayerTextHealth`].
 #[derive(Component)]
-pub struct EnemyTextAmmo;
+pub struct StatusText;
+
+/// Tells the player their last move was blocked, until they move again.
+#[derive(Component)]
+pub struct BlockedText;
+
+/// Marks the entities of the end screen.
+#[derive(Component)]
+pub struct EndScreen;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndButton {
+    PlayAgain,
+    MainMenu,
+    Quit,
+}
+
+/// The outcome of the match, kept until the end screen is showing. A match decided while
+/// paused ends once it is resumed.
+#[derive(Default)]
+pub struct PendingGameOver(pub Option<crate::GameOverEvent>);
 
 pub fn game_over(
+    mut commands: Commands,
     mut app_state: ResMut<State<crate::AppState>>,
-    player: Query<&crate::Health, (With<crate::Player>, Without<crate::Enemy>)>,
-    enemy: Query<&crate::Health, (With<crate::Enemy>, Without<crate::Player>)>,
+    mut game_over: EventReader<crate::GameOverEvent>,
+    mut pending: ResMut<PendingGameOver>,
 ) {
-    for health in player.iter() {
-        if health.value <= 0 {
-            match app_state.current() {
-                crate::AppState::InGame => app_state.set(crate::AppState::Lose).unwrap(),
-                _ => (),
-            }
-        }
+    if let Some(event) = game_over.iter().last() {
+        pending.0 = Some(*event);
     }
-    for health in enemy.iter() {
-        if health.value <= 0 {
-            match app_state.current() {
-                crate::AppState::InGame => app_state.set(crate::AppState::Win).unwrap(),
-                _ => (),
-            }
+    let event = match pending.0 {
+        Some(event) => event,
+        None => return,
+    };
+    match app_state.current() {
+        crate::AppState::InGame => {
+            // Overwrites a pause queued in the same frame; the match is over anyway.
+            app_state
+                .overwrite_set(match event.outcome.winner {
+                    Some(Side::Player) => crate::AppState::Win,
+                    Some(Side::Enemy) => crate::AppState::Lose,
+                    None => crate::AppState::Draw,
+                })
+                .unwrap();
+            commands.insert_resource(event);
         }
+        crate::AppState::Win | crate::AppState::Lose | crate::AppState::Draw => pending.0 = None,
+        _ => (),
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn button_system(
-    app_state: ResMut<State<crate::AppState>>,
+    mut app_state: ResMut<State<crate::AppState>>,
+    mut exit: EventWriter<AppExit>,
     mut interaction_query: Query<
-        (&Interaction, &mut UiColor, &Children),
+        (&Interaction, &mut UiColor, &EndButton),
         (Changed<Interaction>, With<Button>),
     >,
-    mut text_query: Query<&mut Text>,
 ) {
-    for (interaction, mut color, children) in interaction_query.iter_mut() {
-        let mut text = text_query.get_mut(children[0]).unwrap();
+    for (interaction, mut color, button) in interaction_query.iter_mut() {
         match *interaction {
             Interaction::Clicked => {
-                text.sections[0].value = "exit".to_string();
                 *color = PRESSED_BUTTON.into();
-                std::process::exit(0);
+                match button {
+                    EndButton::PlayAgain => app_state.set(crate::AppState::InGame).unwrap(),
+                    EndButton::MainMenu => app_state.set(crate::AppState::MainMenu).unwrap(),
+                    EndButton::Quit => exit.send(AppExit),
+                }
             }
             Interaction::Hovered => {
-                text.sections[0].value = "exit".to_string();
                 *color = HOVERED_BUTTON.into();
             }
             Interaction::None => {
-                match app_state.current() {
-                    crate::AppState::Win => text.sections[0].value = "You Win".to_string(),
-                    crate::AppState::Lose => text.sections[0].value = "You Lose".to_string(),
-                    _ => (),
-                }
                 *color = NORMAL_BUTTON.into();
             }
         }
     }
 }
 
-const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
-const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
-const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
+pub const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+pub const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
+pub const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
+
+fn describe(outcome: Outcome) -> &'static str {
+    match (outcome.reason, outcome.winner) {
+        (GameOverReason::Sunk, Some(Side::Player)) => "The enemy fleet was sunk",
+        (GameOverReason::Sunk, Some(Side::Enemy)) => "Your fleet was sunk",
+        (GameOverReason::Sunk, None) => "Both fleets went down together",
+        (GameOverReason::RanAground, Some(Side::Player)) => "The enemy fleet ran aground",
+        (GameOverReason::RanAground, Some(Side::Enemy)) => "Your fleet ran aground",
+        (GameOverReason::RanAground, None) => "Both fleets ran aground",
+        (GameOverReason::RoundsExpired, Some(Side::Player)) => {
+            "Out of rounds, you took less damage"
+        }
+        (GameOverReason::RoundsExpired, Some(Side::Enemy)) => {
+            "Out of rounds, the enemy took less damage"
+        }
+        (GameOverReason::RoundsExpired, None) => "Out of rounds, both fleets equally battered",
+        (GameOverReason::Surrendered, Some(Side::Player)) => "The enemy struck its colours",
+        (GameOverReason::Surrendered, _) => "You struck your colours",
+    }
+}
+
+pub fn end_message(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    report: Res<crate::GameOverEvent>,
+) {
+    let font = asset_server.load("fonts/FiraMono-Regular.ttf");
+    let text_style = |font_size| TextStyle {
+        font: font.clone(),
+        font_size,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    let title = match report.outcome.winner {
+        Some(Side::Player) => "You Win",
+        Some(Side::Enemy) => "You Lose",
+        None => "Draw",
+    };
+    let fleet_line = |name, health, stats: &FleetStats| {
+        format!(
+            "{}: {} health left, {} hits from {} shots",
+            name, health, stats.hits, stats.shots_fired
+        )
+    };
+    let lines = [
+        (title.to_string(), 40.0),
+        (describe(report.outcome).to_string(), 20.0),
+        (format!("Rounds played: {}", report.rounds), 16.0),
+        (
+            fleet_line("Your fleet", report.player_health, &report.stats.player),
+            16.0,
+        ),
+        (
+            fleet_line("Enemy fleet", report.enemy_health, &report.stats.enemy),
+            16.0,
+        ),
+    ];
 
-pub fn end_message(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.spawn_bundle(UiCameraBundle::default());
     commands
-        .spawn_bundle(ButtonBundle {
+        .spawn_bundle(UiCameraBundle::default())
+        .insert(EndScreen);
+    commands
+        .spawn_bundle(NodeBundle {
             style: Style {
-                size: Size::new(Val::Px(150.0), Val::Px(65.0)),
-                // center button
-                margin: Rect::all(Val::Auto),
-                // horizontally center child text
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                // lay the lines out top to bottom
+                flex_direction: FlexDirection::ColumnReverse,
                 justify_content: JustifyContent::Center,
-                // vertically center child text
                 align_items: AlignItems::Center,
                 ..Default::default()
             },
-            color: NORMAL_BUTTON.into(),
+            color: Color::NONE.into(),
             ..Default::default()
         })
+        .insert(EndScreen)
         .with_children(|parent| {
-            parent.spawn_bundle(TextBundle {
-                text: Text::with_section(
-                    "Button",
-                    TextStyle {
-                        font: asset_server.load("fonts/FiraMono-Regular.ttf"),
-                        font_size: 40.0,
-                        color: Color::rgb(0.9, 0.9, 0.9),
+            for (line, font_size) in lines {
+                parent.spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(5.0)),
+                        ..Default::default()
                     },
-                    Default::default(),
-                ),
-                ..Default::default()
-            });
+                    text: Text::with_section(line, text_style(font_size), Default::default()),
+                    ..Default::default()
+                });
+            }
+            for (button, label) in [
+                (EndButton::PlayAgain, "Play Again"),
+                (EndButton::MainMenu, "Main Menu"),
+                (EndButton::Quit, "Quit"),
+            ] {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(250.0), Val::Px(65.0)),
+                            margin: Rect::all(Val::Px(10.0)),
+                            // horizontally center child text
+                            justify_content: JustifyContent::Center,
+                            // vertically center child text
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        color: NORMAL_BUTTON.into(),
+                        ..Default::default()
+                    })
+                    .insert(button)
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle {
+                            text: Text::with_section(label, text_style(40.0), Default::default()),
+                            ..Default::default()
+                        });
+                    });
+            }
         });
 }
 
+pub fn clear_end_screen(mut commands: Commands, end_screen: Query<Entity, With<EndScreen>>) {
+    for entity in end_screen.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
 pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     let font = asset_server.load("fonts/FiraMono-Regular.ttf");
     let text_style = TextStyle {
@@ -115,26 +244,61 @@ pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         vertical: VerticalAlign::Center,
         horizontal: HorizontalAlign::Left,
     };
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::with_section("Round: {}", text_style.clone(), text_alignment),
+            transform: Transform {
+                translation: Vec3::new(-350.0, -300.0, 0.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(RoundText)
+        .insert(crate::MatchEntity);
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::with_section("", text_style.clone(), text_alignment),
+            transform: Transform {
+                translation: Vec3::new(-100.0, -300.0, 0.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(WindText)
+        .insert(crate::MatchEntity);
     commands
         .spawn_bundle(Text2dBundle {
             text: Text::with_section("Health: {}", text_style.clone(), text_alignment),
             transform: Transform {
-                translation: Vec3::new(-350.0, -330.0, 0.0),
+                translation: Vec3::new(-350.0, -310.0, 0.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(PlayerTextHealth)
+        .insert(crate::MatchEntity);
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::with_section("", text_style.clone(), text_alignment),
+            transform: Transform {
+                translation: Vec3::new(100.0, -310.0, 0.0),
                 ..Default::default()
             },
             ..Default::default()
         })
-        .insert(PlayerTextHealth);
+        .insert(StatusText)
+        .insert(crate::MatchEntity);
     commands
         .spawn_bundle(Text2dBundle {
-            text: Text::with_section("Enemy Health: {}", text_style.clone(), text_alignment),
+            text: Text::with_section("", text_style.clone(), text_alignment),
             transform: Transform {
-                translation: Vec3::new(-350.0, -340.0, 0.0),
+                translation: Vec3::new(100.0, -300.0, 0.0),
                 ..Default::default()
             },
             ..Default::default()
         })
-        .insert(EnemyTextHealth);
+        .insert(BlockedText)
+        .insert(crate::MatchEntity);
     commands
         .spawn_bundle(Text2dBundle {
             text: Text::with_section(
@@ -143,68 +307,268 @@ pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                 text_alignment,
             ),
             transform: Transform {
-                translation: Vec3::new(-350.0, -350.0, 0.0),
+                translation: Vec3::new(-350.0, -320.0, 0.0),
                 ..Default::default()
             },
             ..Default::default()
         })
-        .insert(PlayerTextAmmo);
+        .insert(PlayerTextAmmo)
+        .insert(crate::MatchEntity);
     commands
         .spawn_bundle(Text2dBundle {
-            text: Text::with_section(
-                "Turns till enemy can shoot: {}",
-                text_style.clone(),
-                text_alignment,
-            ),
+            text: Text::with_section("", text_style.clone(), text_alignment),
             transform: Transform {
-                translation: Vec3::new(-350.0, -360.0, 0.0),
+                translation: Vec3::new(-100.0, -320.0, 0.0),
                 ..Default::default()
             },
             ..Default::default()
         })
-        .insert(EnemyTextAmmo);
+        .insert(PlayerTextShot)
+        .insert(crate::MatchEntity);
+    // grows downwards as the fleet gets bigger
+    let fleet_alignment = TextAlignment {
+        vertical: VerticalAlign::Top,
+        ..text_alignment
+    };
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::with_section("", text_style, fleet_alignment),
+            transform: Transform {
+                translation: Vec3::new(-350.0, -326.0, 0.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(EnemyFleetText)
+        .insert(crate::MatchEntity);
+}
+
+/// Shows e.g. `Wind: blowing NE at 70%, you are reaching`.
+pub fn wind_text_update_system(
+    mut wind_text: Query<&mut Text, With<WindText>>,
+    state: Res<GameState>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    const COMPASS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+    let wind = state.wind();
+    let mut line = format!(
+        "Wind: blowing {} at {:.0}%",
+        COMPASS[wind.direction.rem_euclid(8) as usize],
+        wind.strength * 100.0
+    );
+    if let Some((_, ship)) = state.fleet(Side::Player).next() {
+        let point_of_sail = match wind.point_of_sail(ship.direction) {
+            PointOfSail::Running => "running",
+            PointOfSail::Reaching => "reaching",
+            PointOfSail::Beating => "beating",
+            PointOfSail::InIrons => "in irons",
+        };
+        line += &format!(", you are {}", point_of_sail);
+    }
+    for mut text in wind_text.iter_mut() {
+        text.sections[0].value = line.clone();
+    }
+}
+
+/// Icon and name of each status effect.
+fn effect_label(effect: StatusEffect) -> &'static str {
+    match effect {
+        StatusEffect::Stunned => "* stunned",
+        StatusEffect::Burning => "^ burning",
+        StatusEffect::Slowed => "~ slowed",
+        StatusEffect::Dismasted => "# dismasted",
+    }
+}
+
+/// Shows e.g. `^ burning (2) ~ slowed (1)`, with the turns each effect has left.
+fn effects(ship: &Ship) -> String {
+    let labels: Vec<String> = ship
+        .effects
+        .iter()
+        .map(|status| format!("{} ({})", effect_label(status.effect), status.turns))
+        .collect();
+    labels.join(" ")
+}
+
+fn condition(ship: &Ship) -> String {
+    let mut line = format!(
+        "hull {}, sails {}, rudder {}, crew {}",
+        ship.health, ship.sails, ship.rudder, ship.crew
+    );
+    if !ship.effects.is_empty() {
+        line += &format!(", {}", effects(ship));
+    }
+    line
 }
 
 pub fn enemy_text_update_system(
-    mut e_health_text: Query<&mut Text, (With<EnemyTextHealth>, Without<EnemyTextAmmo>)>,
-    mut e_ammo_text: Query<&mut Text, (With<EnemyTextAmmo>, Without<EnemyTextHealth>)>,
-    enemy: Query<
-        (&crate::Health, &crate::ActionPoints),
-        (With<crate::Enemy>, Without<crate::Player>),
-    >,
+    mut fleet_text: Query<&mut Text, With<EnemyFleetText>>,
+    state: Res<GameState>,
 ) {
-    for (e_health, e_ammo) in enemy.iter() {
-        for mut h_text in e_health_text.iter_mut() {
-            h_text.sections[0].value = format!("Enemy Health: {}", e_health.value);
-        }
-        for mut ammo in e_ammo_text.iter_mut() {
-            if 3 - e_ammo.value == 1 {
-                ammo.sections[0].value = format!("Turns till enemy cannon readies: Ready!");
+    if !state.is_changed() {
+        return;
+    }
+    let lines: Vec<String> = state
+        .ships
+        .iter()
+        .filter(|ship| ship.side == Side::Enemy)
+        .enumerate()
+        .map(|(i, ship)| {
+            let status = if !ship.is_afloat() {
+                "sunk".to_string()
+            } else if ship.action_points >= ship.fire_cost() {
+                format!("{}, cannon ready", condition(ship))
             } else {
-                ammo.sections[0].value = format!("Turn till enemy cannon readies: {}", 3 - e_ammo.value);
-            }
-        }
+                format!(
+                    "{}, cannon ready in {} turns",
+                    condition(ship),
+                    ship.fire_cost() - ship.action_points
+                )
+            };
+            format!("Enemy {}: {}", i + 1, status)
+        })
+        .collect();
+    for mut text in fleet_text.iter_mut() {
+        text.sections[0].value = lines.join("\n");
     }
 }
+
+#[allow(clippy::type_complexity)]
 pub fn player_text_update_system(
     mut p_health_text: Query<&mut Text, (With<PlayerTextHealth>, Without<PlayerTextAmmo>)>,
     mut p_ammo_text: Query<&mut Text, (With<PlayerTextAmmo>, Without<PlayerTextHealth>)>,
     player: Query<
-        (&crate::Health, &crate::ActionPoints),
+        (
+            &ShipId,
+            &crate::Health,
+            &crate::Sails,
+            &crate::Rudder,
+            &crate::Crew,
+            &crate::ActionPoints,
+        ),
         (With<crate::Player>, Without<crate::Enemy>),
     >,
+    state: Res<GameState>,
 ) {
-    for (p_health, p_ammo) in player.iter() {
+    for (&ship, p_health, sails, rudder, crew, p_ammo) in player.iter() {
+        let ship = state.ship(ship);
+        let turns = ship.fire_cost() - p_ammo.value;
         for mut h_text in p_health_text.iter_mut() {
-            h_text.sections[0].value = format!("Health: {}", p_health.value);
+            h_text.sections[0].value = format!(
+                "Hull: {}/{}, sails {}/{}, rudder {}/{}, crew {}/{}",
+                p_health.value,
+                ship.hull.health,
+                sails.value,
+                ship.hull.sails,
+                rudder.value,
+                ship.hull.rudder,
+                crew.value,
+                ship.hull.crew
+            );
         }
         for mut h_ammo in p_ammo_text.iter_mut() {
-            if 3 - p_ammo.value == 0 {
-                h_ammo.sections[0].value = format!("Turns till cannon readies: Ready!");
+            if turns <= 0 {
+                h_ammo.sections[0].value = "Turns till cannon readies: Ready!".to_string();
             } else {
-                h_ammo.sections[0].value = format!("Turns till cannon readies: {:?}", 3 - p_ammo.value);
+                h_ammo.sections[0].value = format!("Turns till cannon readies: {:?}", turns);
+            }
+        }
+    }
+}
+
+/// Shows e.g. `[Round] 2:Chain 2 3:Grape 2 4:Heated 1`, with the loaded ammo in brackets
+/// and each special ammo's key and stock.
+pub fn shot_text_update_system(
+    mut shot_text: Query<&mut Text, With<PlayerTextShot>>,
+    state: Res<GameState>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    let ship = match state.fleet(Side::Player).next() {
+        Some((_, ship)) => ship,
+        None => return,
+    };
+    let stocks: Vec<String> = Ammo::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, &ammo)| {
+            let stock = ship
+                .ammo
+                .get(ammo)
+                .map(|stock| format!(" {}", stock))
+                .unwrap_or_default();
+            if ammo == ship.loaded {
+                format!("[{:?}]{}", ammo, stock)
+            } else {
+                format!("{}:{:?}{}", i + 1, ammo, stock)
+            }
+        })
+        .collect();
+    for mut text in shot_text.iter_mut() {
+        text.sections[0].value = stocks.join(" ");
+    }
+}
+
+pub fn status_text_update_system(
+    mut status_text: Query<&mut Text, With<StatusText>>,
+    state: Res<GameState>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    let line = state
+        .fleet(Side::Player)
+        .next()
+        .map(|(_, ship)| effects(ship))
+        .unwrap_or_default();
+    for mut text in status_text.iter_mut() {
+        text.sections[0].value = line.clone();
+    }
+}
+
+pub fn blocked_text_update_system(
+    mut blocked_text: Query<&mut Text, With<BlockedText>>,
+    mut game_events: EventReader<GameEvent>,
+    state: Res<GameState>,
+) {
+    for event in game_events.iter() {
+        let line = match *event {
+            GameEvent::Moved { ship, .. } if state.ship(ship).side == Side::Player => String::new(),
+            GameEvent::Collided { ship, obstacle } if state.ship(ship).side == Side::Player => {
+                match obstacle {
+                    Obstacle::Rock(_) => "Blocked: you ran onto a rock!".to_string(),
+                    Obstacle::Ship(other) if state.ship(other).side == Side::Player => {
+                        "Blocked: you fouled your own ship!".to_string()
+                    }
+                    Obstacle::Ship(_) => "Blocked: you rammed the enemy!".to_string(),
+                    Obstacle::Wreck(_) => "Blocked: you ran onto a wreck!".to_string(),
+                }
+            }
+            GameEvent::Salvaged { ship, ammo, .. } if state.ship(ship).side == Side::Player => {
+                format!(
+                    "Salvaged {} chain, {} grape and {} heated shot from a wreck!",
+                    ammo.chain, ammo.grape, ammo.heated
+                )
             }
-            
+            _ => continue,
+        };
+        for mut text in blocked_text.iter_mut() {
+            text.sections[0].value = line.clone();
         }
     }
 }
+
+pub fn round_text_update_system(
+    mut round_text: Query<&mut Text, With<RoundText>>,
+    state: Res<GameState>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    for mut text in round_text.iter_mut() {
+        text.sections[0].value = format!("Round {} / {}", state.round.current, state.round.max);
+    }
+}